@@ -0,0 +1,105 @@
+// Input vertex attributes
+in vec3 vertexPosition;
+in vec2 vertexTexCoord;
+in vec3 vertexNormal;
+in vec4 vertexColor;
+
+// Input uniform values
+uniform mat4 mvp;
+
+#ifdef INSTANCING
+// Per instance model matrix, mvp holds only view and projection
+in mat4 instanceTransform;
+#else
+uniform mat4 matModel;
+#endif
+
+// Output vertex attributes (to fragment shader)
+out vec3 fragPosition;
+out vec2 fragTexCoord;
+out vec4 fragColor;
+out vec3 fragNormal;
+
+// NOTE: Add here your custom variables
+
+#if defined(RETRO) || defined(OUTLINE)
+// Render target resolution used for vertex snapping and outline width
+uniform vec2 resolution;
+#endif
+
+#ifdef RETRO
+// Texture coordinates premultiplied by w, used for affine texture mapping
+out vec3 fragAffineTexCoord;
+#endif
+
+#ifdef OUTLINE
+// Hull is pushed out along the normals by this many render target pixels
+uniform float outlineWidth;
+#endif
+
+#if __VERSION__ == 100
+// https://github.com/glslify/glsl-inverse
+mat3 inverse(mat3 m)
+{
+  float a00 = m[0][0], a01 = m[0][1], a02 = m[0][2];
+  float a10 = m[1][0], a11 = m[1][1], a12 = m[1][2];
+  float a20 = m[2][0], a21 = m[2][1], a22 = m[2][2];
+
+  float b01 = a22*a11 - a12*a21;
+  float b11 = -a22*a10 + a12*a20;
+  float b21 = a21*a10 - a11*a20;
+
+  float det = a00*b01 + a01*b11 + a02*b21;
+
+  return mat3(b01, (-a22*a01 + a02*a21), (a12*a01 - a02*a11),
+              b11, (a22*a00 - a02*a20), (-a12*a00 + a02*a10),
+              b21, (-a21*a00 + a01*a20), (a11*a00 - a01*a10))/det;
+}
+
+// https://github.com/glslify/glsl-transpose
+mat3 transpose(mat3 m)
+{
+  return mat3(m[0][0], m[1][0], m[2][0],
+              m[0][1], m[1][1], m[2][1],
+              m[0][2], m[1][2], m[2][2]);
+}
+#endif
+
+void main()
+{
+#ifdef INSTANCING
+    mat4 model = instanceTransform;
+#else
+    mat4 model = matModel;
+#endif
+
+    // Send vertex attributes to fragment shader
+    fragPosition = vec3(model*vec4(vertexPosition, 1.0));
+    fragTexCoord = vertexTexCoord;
+    fragColor = vertexColor;
+    
+    mat3 normalMatrix = transpose(inverse(mat3(model)));
+    fragNormal = normalize(normalMatrix*vertexNormal);
+
+    // Calculate final vertex position
+#ifdef INSTANCING
+    gl_Position = mvp*model*vec4(vertexPosition, 1.0);
+#else
+    gl_Position = mvp*vec4(vertexPosition, 1.0);
+#endif
+
+#ifdef OUTLINE
+    // Pushed out in clip space and scaled by w, so the width is the same at any distance
+    vec2 clipNormal = (mvp*vec4(vertexNormal, 0.0)).xy;
+    if (length(clipNormal) > 0.0) gl_Position.xy += normalize(clipNormal)*2.0*outlineWidth/resolution*gl_Position.w;
+#endif
+
+#ifdef RETRO
+    // Snap vertices to the low resolution pixel grid
+    vec2 grid = resolution*0.5;
+    gl_Position.xy = floor(gl_Position.xy/gl_Position.w*grid + 0.5)/grid*gl_Position.w;
+
+    // Dividing by the interpolated w in fragment shader cancels perspective correction
+    fragAffineTexCoord = vec3(vertexTexCoord*gl_Position.w, gl_Position.w);
+#endif
+}
@@ -0,0 +1,30 @@
+// Input vertex attributes (from vertex shader)
+in vec2 fragTexCoord;
+in vec4 fragColor;
+
+// Input uniform values
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+
+// Output fragment color
+out vec4 finalColor;
+
+// Step between samples in texture coordinates, along one axis only
+uniform vec2 direction;
+
+void main()
+{
+    // 9 tap gaussian kernel
+    vec3 result = texture(texture0, fragTexCoord).rgb*0.227027;
+
+    result += texture(texture0, fragTexCoord + direction*1.0).rgb*0.1945946;
+    result += texture(texture0, fragTexCoord - direction*1.0).rgb*0.1945946;
+    result += texture(texture0, fragTexCoord + direction*2.0).rgb*0.1216216;
+    result += texture(texture0, fragTexCoord - direction*2.0).rgb*0.1216216;
+    result += texture(texture0, fragTexCoord + direction*3.0).rgb*0.054054;
+    result += texture(texture0, fragTexCoord - direction*3.0).rgb*0.054054;
+    result += texture(texture0, fragTexCoord + direction*4.0).rgb*0.016216;
+    result += texture(texture0, fragTexCoord - direction*4.0).rgb*0.016216;
+
+    finalColor = vec4(result, 1.0);
+}
@@ -0,0 +1,24 @@
+// Input vertex attributes (from vertex shader)
+in vec2 fragTexCoord;
+in vec4 fragColor;
+
+// Input uniform values
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+
+// Output fragment color
+out vec4 finalColor;
+
+// Colors brighter than threshold are kept
+uniform float threshold;
+
+void main()
+{
+    vec4 texelColor = texture(texture0, fragTexCoord);
+    float brightness = max(texelColor.r, max(texelColor.g, texelColor.b));
+
+    // Subtracting threshold instead of cutting keeps the bloom edge soft
+    float contribution = max(brightness - threshold, 0.0)/max(brightness, 0.0001);
+
+    finalColor = vec4(texelColor.rgb*contribution, 1.0);
+}
@@ -0,0 +1,22 @@
+// Input vertex attributes (from vertex shader)
+in vec2 fragTexCoord;
+in vec4 fragColor;
+
+// Input uniform values
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+
+// Output fragment color
+out vec4 finalColor;
+
+// Blurred bright pass
+uniform sampler2D bloomTexture;
+uniform float intensity;
+
+void main()
+{
+    vec4 texelColor = texture(texture0, fragTexCoord);
+    vec3 bloom = texture(bloomTexture, fragTexCoord).rgb;
+
+    finalColor = vec4(texelColor.rgb + bloom*intensity, texelColor.a);
+}
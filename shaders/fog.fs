@@ -0,0 +1,208 @@
+// Input vertex attributes (from vertex shader)
+in vec2 fragTexCoord;
+in vec4 fragColor;
+in vec3 fragPosition;
+in vec3 fragNormal;
+
+// Input uniform values
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+uniform vec4 colEmission;
+
+// Output fragment color
+out vec4 finalColor;
+
+// NOTE: Add here your custom variables
+
+struct MaterialProperty {
+    vec3 color;
+    int useSampler;
+    sampler2D sampler;
+};
+
+struct Light {
+    int enabled;
+    int type;
+    vec3 position;
+    vec3 target;
+    vec4 color;
+};
+
+// Input lighting values
+uniform Light lights[MAX_LIGHTS];
+uniform vec4 ambient;
+uniform vec3 viewPos;
+uniform float fogDensity;
+// Fog color matches the sky horizon
+uniform vec3 fogColor;
+
+// Animated fog, density is modulated by tiled noise scrolling with the wind
+// and thins out with height. Zero strength and falloff give uniform fog.
+uniform sampler2D fogNoiseMap;
+// Wind offset in noise texture repeats
+uniform vec2 fogScroll;
+// x: world units per noise repeat, y: noise strength, z: height falloff, w: base height
+uniform vec4 fogNoiseParams;
+
+#if FOG_MODE == FOG_MODE_VOLUMETRIC
+#define     FOG_SAMPLES             4
+
+float fogDensityAt(vec3 position)
+{
+    vec2 uv = position.xz/fogNoiseParams.x;
+    // Second layer at another scale and direction hides the tiling
+    float noise = 0.5*(texture(fogNoiseMap, uv - fogScroll).r + texture(fogNoiseMap, uv.yx*2.7 + fogScroll*1.3).r);
+    float height = exp(-max(position.y - fogNoiseParams.w, 0.0)*fogNoiseParams.z);
+
+    return (1.0 + fogNoiseParams.y*(2.0*noise - 1.0))*height;
+}
+
+// Average density scale along the view ray, marched in a few steps so fog looks like a volume
+float fogDensityScale()
+{
+    float density = 0.0;
+    for (int i = 0; i < FOG_SAMPLES; i++) density += fogDensityAt(mix(viewPos, fragPosition, (float(i) + 0.5)/float(FOG_SAMPLES)));
+
+    return density/float(FOG_SAMPLES);
+}
+#else
+// Cheaper, one noise lookup in the middle of the view ray
+float fogDensityScale()
+{
+    vec3 position = mix(viewPos, fragPosition, 0.5);
+    float noise = texture(fogNoiseMap, position.xz/fogNoiseParams.x - fogScroll).r;
+    float height = exp(-max(position.y - fogNoiseParams.w, 0.0)*fogNoiseParams.z);
+
+    return (1.0 + fogNoiseParams.y*(2.0*noise - 1.0))*height;
+}
+#endif
+
+#include "color.glsl"
+
+#ifdef TERRAIN
+// Splat textures, texture0 is the first one
+uniform sampler2D texture1;
+uniform sampler2D texture2;
+
+// Splat weights are stored in the vertex color, one texture per channel
+vec4 splat(vec2 uv)
+{
+    return texture(texture0, uv)*fragColor.r + texture(texture1, uv)*fragColor.g + texture(texture2, uv)*fragColor.b;
+}
+#endif
+
+#ifdef ALPHA_CUTOUT
+// Less opaque texels are discarded
+#define     ALPHA_CUTOFF            0.5
+#endif
+
+#ifdef TOON
+// Diffuse light is looked up in a (bands x 1) ramp, so it changes in hard steps
+uniform sampler2D toonRamp;
+#endif
+
+#ifdef RIM
+// Glow on silhouettes, strength is stored in alpha
+uniform vec4 rimColor;
+uniform float rimPower;
+#endif
+
+#ifdef RETRO
+in vec3 fragAffineTexCoord;
+#endif
+
+void main()
+{
+    // Texel color fetching from texture sampler
+#if defined(TERRAIN)
+    vec4 texelColor = splat(fragTexCoord);
+#elif defined(RETRO)
+    vec4 texelColor = texture(texture0, fragAffineTexCoord.xy/fragAffineTexCoord.z);
+#else
+    vec4 texelColor = texture(texture0, fragTexCoord);
+#endif
+#ifdef ALPHA_CUTOUT
+    // Cutout surfaces write depth, so transparent texels must leave no trace
+    if (texelColor.a*colDiffuse.a < ALPHA_CUTOFF) discard;
+#endif
+    vec3 albedo = srgbToLinear(texelColor.rgb);
+    vec3 lightDot = vec3(0.0);
+    vec3 normal = normalize(fragNormal);
+    vec3 viewD = normalize(viewPos - fragPosition);
+    vec3 specular = vec3(0.0);
+
+    // NOTE: Implement here your fragment shader code
+
+    for (int i = 0; i < MAX_LIGHTS; i++)
+    {
+        if (lights[i].enabled == 1)
+        {
+            vec3 light = vec3(0.0);
+            
+            if (lights[i].type == LIGHT_DIRECTIONAL) light = -normalize(lights[i].target - lights[i].position);
+            if (lights[i].type == LIGHT_POINT) light = normalize(lights[i].position - fragPosition);
+
+            float NdotL = max(dot(normal, light), 0.0);
+#ifdef TOON
+            // Half Lambert spreads the ramp over both sides, the unlit side can have bands too
+            lightDot += srgbToLinear(lights[i].color.rgb)*texture(toonRamp, vec2(0.5*dot(normal, light) + 0.5, 0.5)).r;
+#else
+            lightDot += srgbToLinear(lights[i].color.rgb)*NdotL;
+#endif
+
+            float specCo = 0.0;
+            if (NdotL > 0.0) specCo = pow(max(0.0, dot(viewD, reflect(-(light), normal))), 16.0); // Shine: 16.0
+#ifdef TOON
+            // Highlight is a hard edged spot
+            specCo = step(0.5, specCo);
+#endif
+            specular += specCo;
+        }
+    }
+
+    vec3 diffuse = albedo*srgbToLinear(colDiffuse.rgb);
+    vec3 color = (diffuse + specular)*lightDot;
+    color += albedo*(ambient.rgb/10.0);
+
+#ifdef RIM
+    // Rim takes the light falling on the surface, so it fades in the dark like the rest
+    float rim = pow(1.0 - max(dot(normal, viewD), 0.0), rimPower);
+    color += srgbToLinear(rimColor.rgb)*rimColor.a*rim*(lightDot + ambient.rgb/10.0);
+#endif
+
+    // Emission strength is stored in alpha, strong emission saturates and feeds the bloom pass
+    color += srgbToLinear(colEmission.rgb)*colEmission.a;
+
+#ifdef OUTLINE
+    // Outline hull is one flat color, its inside out faces would light wrong
+    color = srgbToLinear(colDiffuse.rgb);
+#endif
+
+    // Fog calculation
+    float dist = length(viewPos - fragPosition);
+
+    //const float fogDensity = 0.16;
+
+    // Exponential fog
+    float density = fogDensity*fogDensityScale();
+    float fogFactor = 1.0/exp((dist*density)*(dist*density));
+
+    // Linear fog (less nice)
+    //const float fogStart = 2.0;
+    //const float fogEnd = 10.0;
+    //float fogFactor = (fogEnd - dist)/(fogEnd - fogStart);
+
+    fogFactor = clamp(fogFactor, 0.0, 1.0);
+
+    // Fog is blended in linear space, fog color is given in sRGB like every other color
+    color = mix(srgbToLinear(fogColor), color, fogFactor);
+
+    // Linear HDR output, tone mapping and gamma correction happen after bloom
+    finalColor = vec4(encodeScene(color), texelColor.a*colDiffuse.a);
+
+#ifdef RETRO
+    // Zero alpha marks the pixel for palette quantization in the tone mapping pass.
+    // Snapping here would be undone, bloom and tone mapping change the color afterwards.
+    finalColor.a = 0.0;
+#endif
+}
@@ -0,0 +1,48 @@
+// Color space conversion and tone mapping. Lighting and sky shaders output linear
+// color, the tone mapping pass after bloom maps it to the display.
+
+uniform int toneMapper;
+uniform float exposure;
+
+vec3 srgbToLinear(vec3 color)
+{
+    return pow(max(color, 0.0), vec3(2.2));
+}
+
+vec3 linearToSrgb(vec3 color)
+{
+    return pow(max(color, 0.0), vec3(1.0/2.2));
+}
+
+// Scene target stores sRGB encoded color when it has 8 bits per channel,
+// linear 8 bit color would band dark tones
+vec3 encodeScene(vec3 color)
+{
+#ifdef SCENE_SRGB
+    return linearToSrgb(color);
+#else
+    return color;
+#endif
+}
+
+vec3 decodeScene(vec3 color)
+{
+#ifdef SCENE_SRGB
+    return srgbToLinear(color);
+#else
+    return color;
+#endif
+}
+
+// Maps HDR color to displayable [0..1] range
+vec3 toneMap(vec3 color)
+{
+    color *= exposure;
+
+    if (toneMapper == TONEMAP_REINHARD) color = color/(1.0 + color);
+    
+    // Narkowicz ACES filmic curve fit
+    if (toneMapper == TONEMAP_ACES) color = clamp((color*(2.51*color + 0.03))/(color*(2.43*color + 0.59) + 0.14), 0.0, 1.0);
+
+    return color;
+}
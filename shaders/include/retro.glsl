@@ -0,0 +1,44 @@
+// Palette quantization of the retro shader variant, done in the tone mapping
+// pass on pixels the retro materials marked, see tone_map.fs
+
+// Palette colors stored in a (paletteSize x 1) texture
+uniform sampler2D palette;
+uniform int paletteSize;
+uniform float ditherStrength;
+
+// 2x2 Bayer matrix value in [0..3]
+float bayer2(vec2 p)
+{
+    return mod(2.0*p.x + 3.0*p.y, 4.0);
+}
+
+// 4x4 ordered dither threshold in [0..1)
+float bayer4(vec2 p)
+{
+    return (4.0*bayer2(mod(p, 2.0)) + bayer2(mod(floor(p*0.5), 2.0)))/16.0;
+}
+
+// Dithers color and snaps it to the nearest palette entry
+vec3 quantize(vec3 color)
+{
+    vec3 dithered = color + (bayer4(floor(gl_FragCoord.xy)) - 0.5)*ditherStrength;
+    vec3 nearest = color;
+    float nearestDist = 1000.0;
+
+    for (int i = 0; i < MAX_PALETTE_SIZE; i++)
+    {
+        if (i >= paletteSize) break;
+
+        vec3 entry = texture(palette, vec2((float(i) + 0.5)/float(paletteSize), 0.5)).rgb;
+        vec3 diff = dithered - entry;
+        float dist = dot(diff, diff);
+
+        if (dist < nearestDist)
+        {
+            nearest = entry;
+            nearestDist = dist;
+        }
+    }
+
+    return nearest;
+}
@@ -0,0 +1,33 @@
+// Input vertex attributes (from vertex shader)
+in vec3 fragDirection;
+
+#ifdef CUBEMAP
+uniform samplerCube environmentMap;
+#else
+// Gradient colors, horizon color is also used as fog color
+uniform vec3 skyTop;
+uniform vec3 skyHorizon;
+uniform vec3 skyBottom;
+#endif
+
+// Output fragment color
+out vec4 finalColor;
+
+#include "color.glsl"
+
+void main()
+{
+    vec3 direction = normalize(fragDirection);
+
+#ifdef CUBEMAP
+    vec3 color = srgbToLinear(texture(environmentMap, direction).rgb);
+#else
+    // Square root keeps the horizon band narrow
+    float height = sqrt(abs(direction.y));
+    vec3 color = srgbToLinear(skyHorizon);
+    if (direction.y > 0.0) color = mix(color, srgbToLinear(skyTop), height);
+    else color = mix(color, srgbToLinear(skyBottom), height);
+#endif
+
+    finalColor = vec4(encodeScene(color), 1.0);
+}
@@ -0,0 +1,22 @@
+// Input vertex attributes
+in vec3 vertexPosition;
+
+// Input uniform values
+uniform mat4 matProjection;
+uniform mat4 matView;
+
+// Output vertex attributes (to fragment shader)
+out vec3 fragDirection;
+
+void main()
+{
+    // Cube vertices double as view directions
+    fragDirection = vertexPosition;
+
+    // Drop view translation so the sky stays centered on the camera
+    mat4 rotView = mat4(vec4(matView[0].xyz, 0.0), vec4(matView[1].xyz, 0.0), vec4(matView[2].xyz, 0.0), vec4(0.0, 0.0, 0.0, 1.0));
+    vec4 clipPos = matProjection*rotView*vec4(vertexPosition, 1.0);
+
+    // Sky is always at the far plane
+    gl_Position = clipPos.xyww;
+}
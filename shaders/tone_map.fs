@@ -0,0 +1,27 @@
+// Input vertex attributes (from vertex shader)
+in vec2 fragTexCoord;
+in vec4 fragColor;
+
+// Input uniform values
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+
+// Output fragment color
+out vec4 finalColor;
+
+#include "color.glsl"
+#include "retro.glsl"
+
+void main()
+{
+    vec4 texelColor = texture(texture0, fragTexCoord);
+
+    // Scene is linear HDR with bloom added, tone mapping and gamma correction come last
+    vec3 color = linearToSrgb(toneMap(decodeScene(texelColor.rgb)));
+
+    // Retro pixels are snapped to the palette after everything else changed their
+    // color, so they end up as palette colors on screen
+    if (texelColor.a < 0.5) color = quantize(color);
+
+    finalColor = vec4(color, 1.0);
+}
@@ -0,0 +1,21 @@
+// Input vertex attributes (from vertex shader)
+in vec2 fragTexCoord;
+in vec4 fragColor;
+
+// Input uniform values
+uniform sampler2D texture0;
+uniform vec4 colDiffuse;
+
+// Output fragment color
+out vec4 finalColor;
+
+#include "color.glsl"
+
+// Particles and debug shapes skip lighting, their sRGB vertex colors are
+// converted here so the linear scene gets full float precision
+void main()
+{
+    vec4 color = texture(texture0, fragTexCoord)*colDiffuse*fragColor;
+
+    finalColor = vec4(encodeScene(srgbToLinear(color.rgb)), color.a);
+}
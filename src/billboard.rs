@@ -0,0 +1,152 @@
+use crate::culling::*;
+use crate::shader::*;
+use raylib::prelude::*;
+
+// Shader define enabling alpha tested variant of the lighting shader
+pub const ALPHA_CUTOUT_DEFINE: &str = "ALPHA_CUTOUT";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BillboardMode {
+    // Rotates only around world Y, stays upright like a tree
+    Cylindrical,
+    // Always faces the camera, tilts when looked at from above
+    Spherical,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Billboard {
+    // Bottom center of the billboard
+    pub position: Vector3,
+    pub size: Vector2,
+    // Index into the renderer materials
+    pub material: usize,
+    pub mode: BillboardMode,
+    // Translucent billboards are alpha blended and sorted back to front,
+    // opaque ones use an alpha cutout material and write depth
+    pub translucent: bool,
+}
+
+impl Billboard {
+    pub fn bounds(&self) -> BoundingSphere {
+        BoundingSphere {
+            center: self.position + Vector3::new(0.0, self.size.y * 0.5, 0.0),
+            radius: self.size.length() * 0.5,
+        }
+    }
+}
+
+// Billboards are quads drawn with the lighting shader, so they get
+// the same lights and fog as the rest of the scene
+pub struct BillboardRenderer {
+    pub quad: Mesh,
+    pub materials: Vec<WeakMaterial>,
+    pub billboards: Vec<Billboard>,
+    // Translucent billboards sorted by distance, kept to avoid allocating every frame
+    sorted: Vec<(f32, usize)>,
+}
+
+impl BillboardRenderer {
+    pub fn new(thread: &RaylibThread) -> Self {
+        Self {
+            quad: Mesh::gen_mesh_plane(thread, 1.0, 1.0, 1, 1),
+            materials: Vec::new(),
+            billboards: Vec::new(),
+            sorted: Vec::new(),
+        }
+    }
+
+    // Material drawing the texture with the shader, returns its index
+    pub fn add_material(
+        &mut self,
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        shader: &Shader,
+        texture: &Texture2D,
+        tint: Color,
+    ) -> usize {
+        let mut material = rl.load_material_default(thread);
+        material.shader = *shader.as_ref();
+        let map = &mut material.maps_mut()[MaterialMapIndex::MATERIAL_MAP_ALBEDO as usize];
+        map.texture = *texture.as_ref();
+        map.color = tint.into();
+
+        self.materials.push(material);
+        self.materials.len() - 1
+    }
+
+    pub fn draw(&mut self, d: &mut impl RaylibDraw3D, camera: &Camera3D, culling: &mut Culling) {
+        self.sorted.clear();
+
+        for (index, billboard) in self.billboards.iter().enumerate() {
+            if !culling.test(&billboard.bounds()) {
+                continue;
+            }
+
+            if billboard.translucent {
+                let distance = billboard.bounds().center.distance_to(camera.position);
+                self.sorted.push((distance, index));
+                continue;
+            }
+
+            draw_lit_mesh(
+                d,
+                &self.quad,
+                &self.materials[billboard.material],
+                billboard_transform(billboard, camera),
+            );
+        }
+
+        // Farthest first, so nearer ones blend over them
+        self.sorted.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        // Translucent billboards still test depth against the scene
+        // but must not hide each other
+        unsafe {
+            ffi::rlDrawRenderBatchActive();
+            ffi::rlDisableDepthMask();
+        }
+        for (_, index) in &self.sorted {
+            let billboard = &self.billboards[*index];
+            draw_lit_mesh(
+                d,
+                &self.quad,
+                &self.materials[billboard.material],
+                billboard_transform(billboard, camera),
+            );
+        }
+        unsafe {
+            ffi::rlEnableDepthMask();
+        }
+    }
+}
+
+// Model matrix turning the generated XZ plane into a billboard facing the camera
+pub fn billboard_transform(billboard: &Billboard, camera: &Camera3D) -> Matrix {
+    // Stand the plane up facing +Z with its bottom edge at the origin
+    let quad = Matrix::rotate_x(90f32.to_radians())
+        * Matrix::translate(0.0, 0.5, 0.0)
+        * Matrix::scale(billboard.size.x, billboard.size.y, 1.0);
+
+    let to_camera = camera.position - billboard.position;
+    let rotation = match billboard.mode {
+        BillboardMode::Cylindrical => Matrix::rotate_y(to_camera.x.atan2(to_camera.z)),
+        BillboardMode::Spherical => {
+            let forward = to_camera.normalized();
+            let right = camera.up.cross(forward).normalized();
+            let up = forward.cross(right);
+            basis_matrix(right, up, forward)
+        }
+    };
+
+    let position = billboard.position;
+    quad * rotation * Matrix::translate(position.x, position.y, position.z)
+}
+
+// Rotation mapping the X, Y and Z axes onto the given vectors
+fn basis_matrix(x: Vector3, y: Vector3, z: Vector3) -> Matrix {
+    let mut matrix = Matrix::identity();
+    (matrix.m0, matrix.m1, matrix.m2) = (x.x, x.y, x.z);
+    (matrix.m4, matrix.m5, matrix.m6) = (y.x, y.y, y.z);
+    (matrix.m8, matrix.m9, matrix.m10) = (z.x, z.y, z.z);
+    matrix
+}
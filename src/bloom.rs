@@ -0,0 +1,247 @@
+use crate::constants::*;
+use crate::preprocessor::*;
+use raylib::prelude::*;
+
+pub struct BloomSettings {
+    // Linear scene brightness above which colors start to bloom,
+    // at 1.0 only highlights and emission beyond display white bloom
+    pub threshold: f32,
+    // Multiplier of the blurred bright pass added on top of the scene
+    pub intensity: f32,
+    // Spacing between blur samples in bloom texture pixels
+    pub radius: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            threshold: 1.0,
+            intensity: 0.8,
+            radius: 1.5,
+        }
+    }
+}
+
+// Bright pass -> separable gaussian blur at half resolution -> additive composite
+pub struct Bloom {
+    pub settings: BloomSettings,
+    pub enabled: bool,
+    // Scene with bloom applied, drawn to the screen instead of the scene target
+    pub output: RenderTexture2D,
+    bright_pass: Shader,
+    blur: Shader,
+    composite: Shader,
+    threshold_loc: i32,
+    direction_loc: i32,
+    intensity_loc: i32,
+    bloom_texture_loc: i32,
+    ping: RenderTexture2D,
+    pong: RenderTexture2D,
+}
+
+impl Bloom {
+    pub fn new(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        width: u32,
+        height: u32,
+        settings: BloomSettings,
+    ) -> Self {
+        let bright_pass_shader = shader_source(BLOOM_BRIGHT_SHADER, ShaderStage::Fragment, &[]);
+        let blur_shader = shader_source(BLOOM_BLUR_SHADER, ShaderStage::Fragment, &[]);
+        let composite_shader = shader_source(BLOOM_COMPOSITE_SHADER, ShaderStage::Fragment, &[]);
+
+        // Default raylib vertex shader is used for all passes
+        let bright_pass = rl.load_shader_from_memory(thread, None, Some(&bright_pass_shader));
+        let blur = rl.load_shader_from_memory(thread, None, Some(&blur_shader));
+        let composite = rl.load_shader_from_memory(thread, None, Some(&composite_shader));
+
+        // Blur works on half resolution, linear filtering smooths upscaling
+        let ping = load_render_texture_hdr(rl, thread, width / 2, height / 2);
+        let pong = load_render_texture_hdr(rl, thread, width / 2, height / 2);
+        for target in [&ping, &pong] {
+            target
+                .texture()
+                .set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_BILINEAR);
+            target
+                .texture()
+                .set_texture_wrap(thread, TextureWrap::TEXTURE_WRAP_CLAMP);
+        }
+
+        Self {
+            settings,
+            enabled: true,
+            output: load_render_texture_hdr(rl, thread, width, height),
+            threshold_loc: bright_pass.get_shader_location("threshold"),
+            direction_loc: blur.get_shader_location("direction"),
+            intensity_loc: composite.get_shader_location("intensity"),
+            bloom_texture_loc: composite.get_shader_location("bloomTexture"),
+            bright_pass,
+            blur,
+            composite,
+            ping,
+            pong,
+        }
+    }
+
+    // Runs all bloom passes over the scene, result is stored in output
+    pub fn apply(
+        &mut self,
+        d: &mut RaylibDrawHandle,
+        thread: &RaylibThread,
+        scene: &RenderTexture2D,
+    ) {
+        let width = self.ping.texture.width as f32;
+        let height = self.ping.texture.height as f32;
+
+        self.bright_pass
+            .set_shader_value(self.threshold_loc, self.settings.threshold);
+
+        // Bright pass, downsampled to half resolution
+        {
+            let shader = *self.bright_pass.as_ref();
+            let mut d = d.begin_texture_mode(thread, &mut self.ping);
+            let mut d = d.begin_shader_mode(&shader);
+            draw_render_texture(&mut d, scene, width, height);
+        }
+
+        // Horizontal blur
+        {
+            self.blur.set_shader_value(
+                self.direction_loc,
+                Vector2::new(self.settings.radius / width, 0.0),
+            );
+            let shader = *self.blur.as_ref();
+            let mut d = d.begin_texture_mode(thread, &mut self.pong);
+            let mut d = d.begin_shader_mode(&shader);
+            draw_render_texture(&mut d, &self.ping, width, height);
+        }
+
+        // Vertical blur
+        {
+            self.blur.set_shader_value(
+                self.direction_loc,
+                Vector2::new(0.0, self.settings.radius / height),
+            );
+            let shader = *self.blur.as_ref();
+            let mut d = d.begin_texture_mode(thread, &mut self.ping);
+            let mut d = d.begin_shader_mode(&shader);
+            draw_render_texture(&mut d, &self.pong, width, height);
+        }
+
+        // Additive composite on top of the scene
+        {
+            self.composite
+                .set_shader_value(self.intensity_loc, self.settings.intensity);
+            let shader = *self.composite.as_ref();
+            let mut d = d.begin_texture_mode(thread, &mut self.output);
+            let mut d = d.begin_shader_mode(&shader);
+            // NOTE: Additional samplers have to be set after shader mode begins
+            self.composite
+                .set_shader_value_texture(self.bloom_texture_loc, self.ping.texture());
+            // Scene alpha is copied as it is, retro pixels are marked with zero alpha
+            unsafe {
+                ffi::rlDrawRenderBatchActive();
+                ffi::rlDisableColorBlend();
+            }
+            draw_render_texture(
+                &mut d,
+                scene,
+                scene.texture.width as f32,
+                scene.texture.height as f32,
+            );
+            unsafe {
+                ffi::rlDrawRenderBatchActive();
+                ffi::rlEnableColorBlend();
+            }
+        }
+    }
+}
+
+// Render textures are stored upside down, negative source height flips them back
+fn draw_render_texture(d: &mut impl RaylibDraw, target: &RenderTexture2D, width: f32, height: f32) {
+    d.draw_texture_pro(
+        target.texture(),
+        Rectangle::new(
+            0f32,
+            0f32,
+            target.texture.width as f32,
+            -target.texture.height as f32,
+        ),
+        Rectangle::new(0f32, 0f32, width, height),
+        Vector2::zero(),
+        0f32,
+        Color::WHITE,
+    );
+}
+
+// Same as load_render_texture, but with a half float color buffer so lighting
+// can go above 1.0, falls back to RGBA8 where float framebuffers aren't supported.
+// Check the fallback with is_hdr.
+pub fn load_render_texture_hdr(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    width: u32,
+    height: u32,
+) -> RenderTexture2D {
+    let (width, height) = (width as i32, height as i32);
+    let format = PixelFormat::PIXELFORMAT_UNCOMPRESSED_R16G16B16A16 as i32;
+
+    unsafe {
+        let id = ffi::rlLoadFramebuffer(width, height);
+        if id > 0 {
+            ffi::rlEnableFramebuffer(id);
+
+            let texture = ffi::Texture {
+                id: ffi::rlLoadTexture(std::ptr::null(), width, height, format, 1),
+                width,
+                height,
+                mipmaps: 1,
+                format,
+            };
+            let depth = ffi::Texture {
+                id: ffi::rlLoadTextureDepth(width, height, true),
+                width,
+                height,
+                mipmaps: 1,
+                format: 19, // DEPTH_COMPONENT_24BIT, same as raylib
+            };
+
+            ffi::rlFramebufferAttach(
+                id,
+                texture.id,
+                ffi::rlFramebufferAttachType::RL_ATTACHMENT_COLOR_CHANNEL0 as i32,
+                ffi::rlFramebufferAttachTextureType::RL_ATTACHMENT_TEXTURE2D as i32,
+                0,
+            );
+            ffi::rlFramebufferAttach(
+                id,
+                depth.id,
+                ffi::rlFramebufferAttachType::RL_ATTACHMENT_DEPTH as i32,
+                ffi::rlFramebufferAttachTextureType::RL_ATTACHMENT_RENDERBUFFER as i32,
+                0,
+            );
+
+            let is_complete = ffi::rlFramebufferComplete(id);
+            ffi::rlDisableFramebuffer();
+
+            if is_complete {
+                return RenderTexture2D::from_raw(ffi::RenderTexture2D { id, texture, depth });
+            }
+
+            ffi::UnloadRenderTexture(ffi::RenderTexture2D { id, texture, depth });
+        }
+    }
+
+    rl.trace_log(
+        TraceLogLevel::LOG_WARNING,
+        "BLOOM: Float render targets not supported, falling back to RGBA8",
+    );
+    rl.load_render_texture(thread, width as u32, height as u32)
+        .unwrap()
+}
+
+// False for the RGBA8 fallback of load_render_texture_hdr
+pub fn is_hdr(target: &RenderTexture2D) -> bool {
+    target.texture.format == PixelFormat::PIXELFORMAT_UNCOMPRESSED_R16G16B16A16 as i32
+}
@@ -0,0 +1,546 @@
+use crate::culling::*;
+use raylib::prelude::*;
+
+// Input for one frame, read once so camera modes don't depend on the handle
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CameraInput {
+    // x right, y up, z forward, each in [-1..1]
+    pub movement: Vector3,
+    // Mouse movement in pixels while the right button is held
+    pub look: Vector2,
+    pub zoom: f32,
+}
+
+impl CameraInput {
+    pub fn read(rl: &RaylibHandle) -> Self {
+        let axis = |positive: KeyboardKey, negative: KeyboardKey| {
+            rl.is_key_down(positive) as i32 as f32 - rl.is_key_down(negative) as i32 as f32
+        };
+
+        let look = if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_RIGHT) {
+            rl.get_mouse_delta()
+        } else {
+            Vector2::zero()
+        };
+
+        Self {
+            movement: Vector3::new(
+                axis(KeyboardKey::KEY_D, KeyboardKey::KEY_A),
+                axis(KeyboardKey::KEY_E, KeyboardKey::KEY_Q),
+                axis(KeyboardKey::KEY_W, KeyboardKey::KEY_S),
+            ),
+            look,
+            zoom: rl.get_mouse_wheel_move(),
+        }
+    }
+}
+
+// Where the camera is and what it looks at
+#[derive(Clone, Copy, Debug)]
+pub struct CameraPose {
+    pub position: Vector3,
+    pub target: Vector3,
+}
+
+impl CameraPose {
+    pub fn lerp(&self, other: &CameraPose, amount: f32) -> CameraPose {
+        CameraPose {
+            position: self.position.lerp(other.position, amount),
+            target: self.target.lerp(other.target, amount),
+        }
+    }
+}
+
+// Angles are in radians
+pub struct OrbitalCamera {
+    pub center: Vector3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub radius: f32,
+    // Automatic rotation around the center
+    pub speed: f32,
+    pub min_pitch: f32,
+    pub max_pitch: f32,
+    pub min_radius: f32,
+    pub max_radius: f32,
+    pub look_sensitivity: f32,
+}
+
+impl Default for OrbitalCamera {
+    fn default() -> Self {
+        Self {
+            center: Vector3::zero(),
+            yaw: 0.0,
+            pitch: 45f32.to_radians(),
+            radius: 14.0,
+            speed: 0.5,
+            min_pitch: 5f32.to_radians(),
+            max_pitch: 85f32.to_radians(),
+            min_radius: 2.0,
+            max_radius: 40.0,
+            look_sensitivity: 0.005,
+        }
+    }
+}
+
+impl OrbitalCamera {
+    pub fn update(&mut self, input: &CameraInput, delta_time: f32) -> CameraPose {
+        self.yaw += self.speed * delta_time - input.look.x * self.look_sensitivity;
+        self.pitch = (self.pitch + input.look.y * self.look_sensitivity)
+            .clamp(self.min_pitch, self.max_pitch);
+        self.radius = (self.radius - input.zoom).clamp(self.min_radius, self.max_radius);
+
+        self.pose()
+    }
+
+    pub fn pose(&self) -> CameraPose {
+        CameraPose {
+            position: self.center + direction_from_angles(self.yaw, self.pitch) * self.radius,
+            target: self.center,
+        }
+    }
+}
+
+pub struct FreeFlyCamera {
+    pub position: Vector3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub move_speed: f32,
+    pub look_sensitivity: f32,
+}
+
+impl Default for FreeFlyCamera {
+    fn default() -> Self {
+        Self {
+            position: Vector3::new(0.0, 2.0, 10.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            move_speed: 6.0,
+            look_sensitivity: 0.003,
+        }
+    }
+}
+
+impl FreeFlyCamera {
+    pub fn update(&mut self, input: &CameraInput, delta_time: f32) -> CameraPose {
+        self.yaw -= input.look.x * self.look_sensitivity;
+        self.pitch = (self.pitch + input.look.y * self.look_sensitivity)
+            .clamp(-89f32.to_radians(), 89f32.to_radians());
+
+        // Camera looks against the direction it would be placed at by the orbit
+        let forward = -direction_from_angles(self.yaw, self.pitch);
+        let right = forward.cross(Vector3::up()).normalized();
+
+        self.position += (right * input.movement.x
+            + Vector3::up() * input.movement.y
+            + forward * input.movement.z)
+            * self.move_speed
+            * delta_time;
+
+        self.pose()
+    }
+
+    pub fn pose(&self) -> CameraPose {
+        CameraPose {
+            position: self.position,
+            target: self.position - direction_from_angles(self.yaw, self.pitch),
+        }
+    }
+
+    // Continues flying from where another mode left the camera
+    pub fn look_from(&mut self, pose: &CameraPose) {
+        let back = (pose.position - pose.target).normalized();
+        self.position = pose.position;
+        self.yaw = back.x.atan2(back.z);
+        self.pitch = back.y.clamp(-1.0, 1.0).asin();
+    }
+}
+
+// Third person camera pulled behind the target by a damped spring
+pub struct FollowCamera {
+    pub target: Vector3,
+    // Desired camera position relative to the target
+    pub offset: Vector3,
+    pub stiffness: f32,
+    pub damping: f32,
+    // Distance kept between the camera and obstacles
+    pub collision_radius: f32,
+    // Obstacles never pull the camera closer to the target than this
+    pub min_distance: f32,
+    pub position: Vector3,
+    pub velocity: Vector3,
+}
+
+impl Default for FollowCamera {
+    fn default() -> Self {
+        Self {
+            target: Vector3::zero(),
+            offset: Vector3::new(0.0, 3.0, 6.0),
+            stiffness: 40.0,
+            // 2 * sqrt(stiffness) is critically damped
+            damping: 12.6,
+            collision_radius: 0.3,
+            min_distance: 1.0,
+            position: Vector3::new(0.0, 3.0, 6.0),
+            velocity: Vector3::zero(),
+        }
+    }
+}
+
+impl FollowCamera {
+    pub fn update(
+        &mut self,
+        target: Vector3,
+        obstacles: &[BoundingSphere],
+        delta_time: f32,
+    ) -> CameraPose {
+        self.target = target;
+
+        let desired = target + self.offset;
+        let acceleration =
+            (desired - self.position) * self.stiffness - self.velocity * self.damping;
+        self.velocity += acceleration * delta_time;
+        self.position += self.velocity * delta_time;
+
+        // Obstacles between the target and the camera pull it closer,
+        // ones the target is inside of would put the camera on the target
+        let to_camera = self.position - target;
+        let distance = to_camera.length();
+        if distance > 0.0 {
+            let direction = to_camera / distance;
+            let mut max_distance = distance;
+            for obstacle in obstacles {
+                let sphere = BoundingSphere {
+                    center: obstacle.center,
+                    radius: obstacle.radius + self.collision_radius,
+                };
+                if sphere.center.distance_to(target) < sphere.radius {
+                    continue;
+                }
+                if let Some(hit) = ray_sphere_distance(target, direction, &sphere) {
+                    max_distance = max_distance.min(hit);
+                }
+            }
+            self.position = target + direction * max_distance.max(self.min_distance);
+        }
+
+        self.pose()
+    }
+
+    pub fn pose(&self) -> CameraPose {
+        CameraPose {
+            position: self.position,
+            target: self.target,
+        }
+    }
+}
+
+// Moves along a looping Catmull-Rom spline through the points
+pub struct RailCamera {
+    pub points: Vec<Vector3>,
+    pub target: Vector3,
+    // Spline segments per second
+    pub speed: f32,
+    pub progress: f32,
+}
+
+impl RailCamera {
+    pub fn new(points: Vec<Vector3>, target: Vector3, speed: f32) -> Self {
+        Self {
+            points,
+            target,
+            speed,
+            progress: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) -> CameraPose {
+        if !self.points.is_empty() {
+            self.progress = (self.progress + self.speed * delta_time) % self.points.len() as f32;
+        }
+
+        self.pose()
+    }
+
+    pub fn pose(&self) -> CameraPose {
+        CameraPose {
+            position: rail_position(&self.points, self.progress),
+            target: self.target,
+        }
+    }
+}
+
+pub fn rail_position(points: &[Vector3], progress: f32) -> Vector3 {
+    if points.is_empty() {
+        return Vector3::zero();
+    }
+
+    let count = points.len() as i32;
+    let segment = progress.floor() as i32;
+    let t = progress - progress.floor();
+    let point = |i: i32| points[(segment + i).rem_euclid(count) as usize];
+
+    catmull_rom(point(-1), point(0), point(1), point(2), t)
+}
+
+pub fn catmull_rom(p0: Vector3, p1: Vector3, p2: Vector3, p3: Vector3, t: f32) -> Vector3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+// Unit vector pointing from the orbit center to the camera
+pub fn direction_from_angles(yaw: f32, pitch: f32) -> Vector3 {
+    Vector3::new(
+        pitch.cos() * yaw.sin(),
+        pitch.sin(),
+        pitch.cos() * yaw.cos(),
+    )
+}
+
+// Distance along a normalized ray to the first sphere intersection in front of the origin
+pub fn ray_sphere_distance(
+    origin: Vector3,
+    direction: Vector3,
+    sphere: &BoundingSphere,
+) -> Option<f32> {
+    let to_center = sphere.center - origin;
+    let along = to_center.dot(direction);
+    let closest_squared = to_center.dot(to_center) - along * along;
+    let radius_squared = sphere.radius * sphere.radius;
+    if closest_squared > radius_squared {
+        return None;
+    }
+
+    let half_chord = (radius_squared - closest_squared).sqrt();
+    let near = along - half_chord;
+    if near >= 0.0 {
+        Some(near)
+    } else if along + half_chord >= 0.0 {
+        // Origin is inside the sphere
+        Some(0.0)
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ControllerMode {
+    Orbital,
+    FreeFly,
+    Follow,
+    Rail,
+}
+
+impl ControllerMode {
+    pub fn next(self) -> Self {
+        match self {
+            ControllerMode::Orbital => ControllerMode::FreeFly,
+            ControllerMode::FreeFly => ControllerMode::Follow,
+            ControllerMode::Follow => ControllerMode::Rail,
+            ControllerMode::Rail => ControllerMode::Orbital,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ControllerMode::Orbital => "orbital",
+            ControllerMode::FreeFly => "free fly",
+            ControllerMode::Follow => "follow",
+            ControllerMode::Rail => "rail",
+        }
+    }
+}
+
+// Drives a Camera3D with one of the modes, switching modes blends between them
+pub struct CameraController {
+    pub mode: ControllerMode,
+    pub orbital: OrbitalCamera,
+    pub free_fly: FreeFlyCamera,
+    pub follow: FollowCamera,
+    pub rail: RailCamera,
+    // Seconds a mode switch takes
+    pub blend_time: f32,
+    blend_from: CameraPose,
+    blend: f32,
+}
+
+impl CameraController {
+    pub fn new(rail: RailCamera) -> Self {
+        let orbital = OrbitalCamera::default();
+        let pose = orbital.pose();
+
+        Self {
+            mode: ControllerMode::Orbital,
+            orbital,
+            free_fly: FreeFlyCamera::default(),
+            follow: FollowCamera::default(),
+            rail,
+            blend_time: 1.0,
+            blend_from: pose,
+            blend: 1.0,
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: ControllerMode, camera: &Camera3D) {
+        if mode == self.mode {
+            return;
+        }
+
+        self.blend_from = CameraPose {
+            position: camera.position,
+            target: camera.target,
+        };
+        self.blend = 0.0;
+        self.mode = mode;
+
+        // Free fly and follow start from the current view instead of a jump
+        match mode {
+            ControllerMode::FreeFly => self.free_fly.look_from(&self.blend_from),
+            ControllerMode::Follow => {
+                self.follow.position = camera.position;
+                self.follow.velocity = Vector3::zero();
+            }
+            _ => {}
+        }
+    }
+
+    // follow_target and obstacles are used only by the follow mode
+    pub fn update(
+        &mut self,
+        camera: &mut Camera3D,
+        input: &CameraInput,
+        follow_target: Vector3,
+        obstacles: &[BoundingSphere],
+        delta_time: f32,
+    ) {
+        let pose = match self.mode {
+            ControllerMode::Orbital => self.orbital.update(input, delta_time),
+            ControllerMode::FreeFly => self.free_fly.update(input, delta_time),
+            ControllerMode::Follow => self.follow.update(follow_target, obstacles, delta_time),
+            ControllerMode::Rail => self.rail.update(delta_time),
+        };
+
+        let pose = if self.blend < 1.0 {
+            self.blend = (self.blend + delta_time / self.blend_time.max(f32::EPSILON)).min(1.0);
+            self.blend_from.lerp(&pose, smoothstep(self.blend))
+        } else {
+            pose
+        };
+
+        camera.position = pose.position;
+        camera.target = pose.target;
+    }
+}
+
+pub fn smoothstep(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// 2D camera following a target around a world larger than the view
+pub struct FollowCamera2D {
+    pub camera: Camera2D,
+    // Half size of the area around the view center the target moves in
+    // without moving the camera
+    pub dead_zone: Vector2,
+    // Distance in pixels the view leads the target in its moving direction
+    pub look_ahead: f32,
+    // Higher is snappier, 0.0 freezes the camera
+    pub smoothing: f32,
+    // The view never shows anything outside of the bounds
+    pub bounds: Rectangle,
+    focus: Vector2,
+    lead: Vector2,
+}
+
+impl FollowCamera2D {
+    pub fn new(view_size: Vector2, bounds: Rectangle, target: Vector2) -> Self {
+        let half_view = view_size * 0.5;
+        let center = clamp_view_center(target, half_view, &bounds);
+
+        Self {
+            camera: Camera2D {
+                offset: half_view,
+                target: center,
+                rotation: 0.0,
+                zoom: 1.0,
+            },
+            dead_zone: Vector2::new(24.0, 16.0),
+            look_ahead: 32.0,
+            smoothing: 6.0,
+            bounds,
+            focus: target,
+            lead: Vector2::zero(),
+        }
+    }
+
+    pub fn update(&mut self, target: Vector2, direction: Vector2, delta_time: f32) {
+        self.focus = dead_zone_follow(self.focus, target, self.dead_zone);
+
+        // Frame rate independent exponential smoothing
+        let amount = 1.0 - (-self.smoothing * delta_time).exp();
+        self.lead = self.lead.lerp(direction * self.look_ahead, amount);
+
+        let goal = self.camera.target.lerp(self.focus + self.lead, amount);
+        self.camera.target = clamp_view_center(goal, self.camera.offset, &self.bounds);
+    }
+}
+
+// Moves focus only as far as needed to keep target inside the dead zone around it
+pub fn dead_zone_follow(focus: Vector2, target: Vector2, dead_zone: Vector2) -> Vector2 {
+    let axis = |focus: f32, target: f32, zone: f32| {
+        let diff = target - focus;
+        if diff > zone {
+            target - zone
+        } else if diff < -zone {
+            target + zone
+        } else {
+            focus
+        }
+    };
+
+    Vector2::new(
+        axis(focus.x, target.x, dead_zone.x),
+        axis(focus.y, target.y, dead_zone.y),
+    )
+}
+
+// Keeps the view inside bounds, views larger than bounds are centered on them
+pub fn clamp_view_center(center: Vector2, half_view: Vector2, bounds: &Rectangle) -> Vector2 {
+    let axis = |center: f32, half_view: f32, start: f32, size: f32| {
+        if size <= half_view * 2.0 {
+            start + size * 0.5
+        } else {
+            center.clamp(start + half_view, start + size - half_view)
+        }
+    };
+
+    Vector2::new(
+        axis(center.x, half_view.x, bounds.x, bounds.width),
+        axis(center.y, half_view.y, bounds.y, bounds.height),
+    )
+}
+
+// World area seen through a 2D camera, rotated views are covered by their bounding box
+pub fn view_rect_2d(camera: &Camera2D, view_size: Vector2) -> Rectangle {
+    let half_view = view_size * (0.5 / camera.zoom);
+    let (sin, cos) = camera.rotation.to_radians().sin_cos();
+    let half_width = half_view.x * cos.abs() + half_view.y * sin.abs();
+    let half_height = half_view.x * sin.abs() + half_view.y * cos.abs();
+
+    // offset is where target ends up on screen, so the view isn't always centered on it
+    let center = camera.target + (view_size * 0.5 - camera.offset) * (1.0 / camera.zoom);
+
+    Rectangle::new(
+        center.x - half_width,
+        center.y - half_height,
+        half_width * 2.0,
+        half_height * 2.0,
+    )
+}
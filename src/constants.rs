@@ -2,6 +2,9 @@
 pub const SCREEN_WIDTH: f32 = 640f32 / 2f32;
 pub const SCREEN_HEIGHT: f32 = 480f32 / 2f32;
 pub const VERSION_NAME: &str = "Sound fix";
+// 2D world is larger than the render target, camera follows the ball around it
+pub const WORLD_WIDTH: f32 = SCREEN_WIDTH * 3f32;
+pub const WORLD_HEIGHT: f32 = SCREEN_HEIGHT * 3f32;
 
 
 /* Audio*/
@@ -13,15 +16,35 @@ pub const TEXTURE_TEXEL_CHECKER: &[u8; 57153] = include_bytes!("../assets/texel_
 pub const TEXTURE_GROUND: &[u8; 119400] = include_bytes!("../assets/ground.png");
 pub const TEXTURE_TREE_LEFT: &[u8; 16708] = include_bytes!("../assets/tree_left.png");
 pub const TEXTURE_TREE_RIGHT: &[u8; 15178] = include_bytes!("../assets/tree_right.png");
+pub const TEXTURE_HEIGHTMAP: &[u8; 9971] = include_bytes!("../assets/heightmap.png");
+
+/* Maps */
+pub const LEVEL_MAP: &str = include_str!("../assets/level.tmj");
+
+/* Models */
+pub const MODEL_COLUMN: &[u8; 7224] = include_bytes!("../assets/column.gltf");
+pub const MODEL_CRATE: &[u8; 446] = include_bytes!("../assets/crate.obj");
+
+/* Scenes */
+pub const SCENE: &str = include_str!("../assets/scene.json");
 
 /* Shaders */
 #[cfg(not(target_arch = "wasm32"))]
 pub const GLSL_VERSION: i32 = 330;
 #[cfg(target_arch = "wasm32")]
 pub const GLSL_VERSION: i32 = 100;
-// GLSL 330
-pub const FRACTAL_SHADER_GLSL330: &str = include_str!("../shaders/glsl330/fog.fs");
-pub const VERTEX_SHADER_GLSL330: &str = include_str!("../shaders/glsl330/base_lighting.vs");
-// GLSL 100
-pub const FRACTAL_SHADER_GLSL100: &str = include_str!("../shaders/glsl100/fog.fs");
-pub const VERTEX_SHADER_GLSL100: &str = include_str!("../shaders/glsl100/base_lighting.vs");
+// Single sources, GLSL 100 is generated from them by the preprocessor
+pub const FRACTAL_SHADER: &str = include_str!("../shaders/fog.fs");
+pub const VERTEX_SHADER: &str = include_str!("../shaders/base_lighting.vs");
+pub const BLOOM_BRIGHT_SHADER: &str = include_str!("../shaders/bloom_bright.fs");
+pub const BLOOM_BLUR_SHADER: &str = include_str!("../shaders/bloom_blur.fs");
+pub const BLOOM_COMPOSITE_SHADER: &str = include_str!("../shaders/bloom_composite.fs");
+pub const SKY_VERTEX_SHADER: &str = include_str!("../shaders/sky.vs");
+pub const SKY_SHADER: &str = include_str!("../shaders/sky.fs");
+pub const TONE_MAP_SHADER: &str = include_str!("../shaders/tone_map.fs");
+pub const UNLIT_SHADER: &str = include_str!("../shaders/unlit.fs");
+// Chunks shaders can #include by name
+pub const SHADER_CHUNKS: [(&str, &str); 2] = [
+    ("color.glsl", include_str!("../shaders/include/color.glsl")),
+    ("retro.glsl", include_str!("../shaders/include/retro.glsl")),
+];
@@ -0,0 +1,247 @@
+use raylib::prelude::*;
+
+// Same clip planes raylib uses for 3D mode (RL_CULL_DISTANCE_NEAR/FAR)
+pub const CULL_DISTANCE_NEAR: f32 = 0.01;
+pub const CULL_DISTANCE_FAR: f32 = 1000.0;
+
+// Fog factor below which an object is invisible in an 8 bit render target
+pub const FOG_VISIBILITY_THRESHOLD: f32 = 1.0 / 255.0;
+
+#[derive(Clone, Copy, Debug)]
+pub struct BoundingSphere {
+    pub center: Vector3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    // Smallest sphere containing the box
+    pub fn from_box(bounds: &BoundingBox) -> Self {
+        Self {
+            center: (bounds.min + bounds.max) * 0.5,
+            radius: (bounds.max - bounds.min).length() * 0.5,
+        }
+    }
+
+    // Bounds of the first mesh in model space, models here are single mesh
+    pub fn from_mesh(mesh: &WeakMesh) -> Self {
+        Self::from_box(&mesh.get_mesh_bounding_box())
+    }
+
+    // Moves the sphere to world space, radius grows with the largest axis scale
+    pub fn transformed(&self, transform: &Matrix) -> Self {
+        let scale_x = Vector3::new(transform.m0, transform.m1, transform.m2).length();
+        let scale_y = Vector3::new(transform.m4, transform.m5, transform.m6).length();
+        let scale_z = Vector3::new(transform.m8, transform.m9, transform.m10).length();
+
+        Self {
+            center: self.center.transform_with(*transform),
+            radius: self.radius * scale_x.max(scale_y).max(scale_z),
+        }
+    }
+}
+
+// Six clip planes stored as (normal, distance), normals point inside
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    pub planes: [Vector4; 6],
+}
+
+impl Frustum {
+    // Extracts planes from a combined view projection matrix (Gribb & Hartmann)
+    pub fn from_matrix(view_projection: &Matrix) -> Self {
+        let m = view_projection;
+        let row_x = [m.m0, m.m4, m.m8, m.m12];
+        let row_y = [m.m1, m.m5, m.m9, m.m13];
+        let row_z = [m.m2, m.m6, m.m10, m.m14];
+        let row_w = [m.m3, m.m7, m.m11, m.m15];
+
+        // row_w + sign * row
+        let plane = |row: [f32; 4], sign: f32| {
+            normalize_plane(Vector4::new(
+                row_w[0] + sign * row[0],
+                row_w[1] + sign * row[1],
+                row_w[2] + sign * row[2],
+                row_w[3] + sign * row[3],
+            ))
+        };
+
+        let planes = [
+            plane(row_x, 1.0),  // Left
+            plane(row_x, -1.0), // Right
+            plane(row_y, 1.0),  // Bottom
+            plane(row_y, -1.0), // Top
+            plane(row_z, 1.0),  // Near
+            plane(row_z, -1.0), // Far
+        ];
+
+        Self { planes }
+    }
+
+    // Frustum of a perspective camera as set up by begin_mode3D
+    pub fn from_camera(camera: &Camera3D, aspect: f32) -> Self {
+        let view = Matrix::look_at(camera.position, camera.target, camera.up);
+        let projection = Matrix::perspective(
+            camera.fovy.to_radians(),
+            aspect,
+            CULL_DISTANCE_NEAR,
+            CULL_DISTANCE_FAR,
+        );
+
+        // NOTE: Left side is applied first
+        Self::from_matrix(&(view * projection))
+    }
+
+    // True if any part of the sphere may be inside
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane_distance(plane, sphere.center) >= -sphere.radius)
+    }
+}
+
+fn normalize_plane(plane: Vector4) -> Vector4 {
+    let length = Vector3::new(plane.x, plane.y, plane.z).length();
+    if length == 0.0 {
+        return plane;
+    }
+    Vector4::new(
+        plane.x / length,
+        plane.y / length,
+        plane.z / length,
+        plane.w / length,
+    )
+}
+
+fn plane_distance(plane: &Vector4, point: Vector3) -> f32 {
+    plane.x * point.x + plane.y * point.y + plane.z * point.z + plane.w
+}
+
+// Distance at which exponential squared fog in fog.fs hides everything:
+// exp(-(dist*density)^2) = threshold
+pub fn fog_cull_distance(fog_density: f32, threshold: f32) -> f32 {
+    if fog_density <= 0.0 {
+        return f32::INFINITY;
+    }
+    (-threshold.ln()).sqrt() / fog_density
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CullStats {
+    pub drawn: u32,
+    pub culled: u32,
+}
+
+// Per frame visibility test against the camera frustum and fog distance
+pub struct Culling {
+    pub frustum: Frustum,
+    pub view_position: Vector3,
+    pub max_distance: f32,
+    pub stats: CullStats,
+}
+
+impl Culling {
+    pub fn new(camera: &Camera3D, aspect: f32, fog_density: f32) -> Self {
+        Self {
+            frustum: Frustum::from_camera(camera, aspect),
+            view_position: camera.position,
+            max_distance: fog_cull_distance(fog_density, FOG_VISIBILITY_THRESHOLD),
+            stats: CullStats::default(),
+        }
+    }
+
+    pub fn is_visible(&self, sphere: &BoundingSphere) -> bool {
+        let distance = (sphere.center - self.view_position).length() - sphere.radius;
+        distance <= self.max_distance && self.frustum.intersects_sphere(sphere)
+    }
+
+    // Same as is_visible, but counts the result in stats
+    pub fn test(&mut self, sphere: &BoundingSphere) -> bool {
+        let visible = self.is_visible(sphere);
+        if visible {
+            self.stats.drawn += 1;
+        } else {
+            self.stats.culled += 1;
+        }
+        visible
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Looks down -z from the origin
+    fn camera() -> Camera3D {
+        Camera3D::perspective(
+            Vector3::zero(),
+            Vector3::new(0.0, 0.0, -1.0),
+            Vector3::up(),
+            60.0,
+        )
+    }
+
+    fn sphere(x: f32, y: f32, z: f32, radius: f32) -> BoundingSphere {
+        BoundingSphere {
+            center: Vector3::new(x, y, z),
+            radius,
+        }
+    }
+
+    #[test]
+    fn frustum_from_matrix_keeps_spheres_in_front() {
+        let frustum = Frustum::from_camera(&camera(), 1.0);
+
+        assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, -10.0, 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, 10.0, 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere(20.0, 0.0, -10.0, 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere(0.0, -20.0, -10.0, 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, -2000.0, 1.0)));
+    }
+
+    #[test]
+    fn frustum_intersects_spheres_crossing_a_plane() {
+        let frustum = Frustum::from_camera(&camera(), 1.0);
+
+        // Half angle is 30 degrees, the left plane is at x = -tan(30) * 10 = -5.77
+        assert!(frustum.intersects_sphere(&sphere(-6.5, 0.0, -10.0, 1.0)));
+        assert!(!frustum.intersects_sphere(&sphere(-6.5, 0.0, -10.0, 0.5)));
+    }
+
+    #[test]
+    fn frustum_planes_are_normalized() {
+        let frustum = Frustum::from_camera(&camera(), 16.0 / 9.0);
+
+        for plane in frustum.planes {
+            let length = Vector3::new(plane.x, plane.y, plane.z).length();
+            assert!((length - 1.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn fog_cull_distance_without_fog_is_infinite() {
+        assert_eq!(
+            fog_cull_distance(0.0, FOG_VISIBILITY_THRESHOLD),
+            f32::INFINITY
+        );
+    }
+
+    #[test]
+    fn fog_cull_distance_reaches_threshold() {
+        for density in [0.05, 0.15, 1.0] {
+            let distance = fog_cull_distance(density, FOG_VISIBILITY_THRESHOLD);
+            let d = distance * density;
+            let fog_factor = (-(d * d)).exp();
+
+            assert!((fog_factor - FOG_VISIBILITY_THRESHOLD).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn culling_skips_spheres_beyond_fog() {
+        let culling = Culling::new(&camera(), 1.0, 0.15);
+        let distance = culling.max_distance;
+
+        assert!(culling.is_visible(&sphere(0.0, 0.0, -distance + 0.5, 1.0)));
+        assert!(!culling.is_visible(&sphere(0.0, 0.0, -distance - 1.5, 1.0)));
+    }
+}
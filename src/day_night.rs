@@ -0,0 +1,246 @@
+use crate::light::*;
+use crate::shader::*;
+use crate::sky::*;
+use raylib::prelude::*;
+use std::f32::consts::PI;
+
+pub const HOURS_PER_DAY: f32 = 24.0;
+
+// Speed multipliers cycled through at runtime, 0.0 stops the clock
+pub const DAY_SPEEDS: [f32; 4] = [1.0, 10.0, 60.0, 0.0];
+
+// Look of the scene at one time of day, values in between are interpolated
+#[derive(Clone, Copy)]
+pub struct DayKeyframe {
+    pub hour: f32,
+    // Sun color, or moon color while the sun is below the horizon
+    pub light_color: Color,
+    pub ambient: f32,
+    // Horizon of the sky gradient is also the fog color
+    pub sky: SkyGradient,
+    // Multiplies the base fog density
+    pub fog_density: f32,
+}
+
+pub struct DayNightSettings {
+    // Keyframes sorted by hour, the last one blends back into the first
+    pub keyframes: Vec<DayKeyframe>,
+    // In game hours per second at speed 1.0
+    pub hours_per_second: f32,
+    // Sun rises here and sets twelve hours later
+    pub sunrise: f32,
+    // Angle the sun path leans from straight overhead towards +Z
+    pub tilt: f32,
+}
+
+impl Default for DayNightSettings {
+    fn default() -> Self {
+        let keyframe = |hour, light_color, ambient, sky, fog_density| DayKeyframe {
+            hour,
+            light_color,
+            ambient,
+            sky,
+            fog_density,
+        };
+        let sky = |top, horizon, bottom| SkyGradient {
+            top,
+            horizon,
+            bottom,
+        };
+
+        let night = sky(
+            Color::new(10, 14, 30, 255),
+            Color::new(30, 34, 52, 255),
+            Color::new(14, 14, 18, 255),
+        );
+        let sunrise = sky(
+            Color::new(80, 100, 160, 255),
+            Color::new(230, 160, 120, 255),
+            Color::new(70, 62, 60, 255),
+        );
+        let sunset = sky(
+            Color::new(60, 70, 130, 255),
+            Color::new(240, 130, 90, 255),
+            Color::new(70, 55, 55, 255),
+        );
+        let moonlight = Color::new(60, 70, 110, 255);
+        let day = SkyGradient::default();
+        let daylight = Color::new(255, 240, 220, 255);
+        let dusk = sky(
+            Color::new(22, 26, 58, 255),
+            Color::new(70, 60, 86, 255),
+            Color::new(30, 28, 32, 255),
+        );
+
+        Self {
+            keyframes: vec![
+                keyframe(0.0, moonlight, 0.08, night, 1.0),
+                keyframe(5.0, moonlight, 0.08, night, 1.2),
+                keyframe(6.5, Color::new(255, 160, 100, 255), 0.14, sunrise, 1.6),
+                keyframe(9.0, daylight, 0.2, day, 1.1),
+                keyframe(12.0, Color::WHITE, 0.2, day, 1.0),
+                keyframe(17.0, daylight, 0.2, day, 1.0),
+                keyframe(18.5, Color::new(255, 120, 70, 255), 0.14, sunset, 1.2),
+                keyframe(20.0, moonlight, 0.1, dusk, 1.0),
+            ],
+            hours_per_second: 0.05,
+            sunrise: 6.0,
+            tilt: 35f32.to_radians(),
+        }
+    }
+}
+
+// Time of day driving a directional sun light, ambient level, sky and fog
+pub struct DayNight {
+    pub settings: DayNightSettings,
+    // Hour of the day in [0..24)
+    pub time: f32,
+    pub speed: f32,
+    // Multipliers of the keyframe values, the weather dims the sun or thickens the fog
+    pub light_scale: f32,
+    pub ambient_scale: f32,
+    pub fog_scale: f32,
+    sun: Light,
+    current: DayKeyframe,
+}
+
+impl DayNight {
+    // Creates the sun light in the next free light slot of the shader,
+    // other shaders get it in apply
+    pub fn new(settings: DayNightSettings, time: f32, shader: &mut Shader) -> Self {
+        let sun = create_light(
+            LightType::LightDirectional,
+            Vector3::up(),
+            Vector3::zero(),
+            Color::WHITE,
+            shader,
+        );
+        let current = sample_keyframes(&settings.keyframes, time);
+
+        let mut day_night = Self {
+            settings,
+            time,
+            speed: DAY_SPEEDS[0],
+            light_scale: 1.0,
+            ambient_scale: 1.0,
+            fog_scale: 1.0,
+            sun,
+            current,
+        };
+        day_night.set_time(time);
+        day_night
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        let hours = self.settings.hours_per_second * self.speed * delta_time;
+        self.set_time(self.time + hours);
+    }
+
+    // Manual scrubbing, works while the clock is stopped
+    pub fn scrub(&mut self, hours: f32) {
+        self.set_time(self.time + hours);
+    }
+
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time.rem_euclid(HOURS_PER_DAY);
+        self.current = sample_keyframes(&self.settings.keyframes, self.time);
+
+        // Either the sun or the moon on the opposite side lights the scene,
+        // fading out at the horizon so the switch between them doesn't pop
+        let sun = sun_direction(self.time, self.settings.sunrise, self.settings.tilt);
+        let direction = if sun.y >= 0.0 { sun } else { -sun };
+        let fade = (direction.y / 0.1).clamp(0.0, 1.0);
+
+        self.sun.position = direction;
+        self.sun.color = scale_color(self.current.light_color, fade);
+    }
+
+    pub fn next_speed(&mut self) {
+        let index = DAY_SPEEDS.iter().position(|speed| *speed == self.speed);
+        self.speed = DAY_SPEEDS[index.map_or(0, |index| (index + 1) % DAY_SPEEDS.len())];
+    }
+
+    pub fn sky(&self) -> &SkyGradient {
+        &self.current.sky
+    }
+
+    pub fn fog_density(&self, base_density: f32) -> f32 {
+        base_density * self.current.fog_density * self.fog_scale
+    }
+
+    // Sun light and ambient level, fog is set together with the other fog uniforms
+    pub fn apply(&self, shader: &mut LightingShader) {
+        let mut sun = self.sun.clone();
+        sun.color = scale_color(sun.color, self.light_scale);
+        shader.set_light(&sun);
+
+        let ambient = self.current.ambient * self.ambient_scale;
+        shader.set_ambient(Vector4::new(ambient, ambient, ambient, ambient));
+    }
+
+    // Time as hh:mm
+    pub fn clock(&self) -> String {
+        let minutes = (self.time * 60.0) as u32;
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+
+// Color with rgb multiplied, alpha is kept
+fn scale_color(color: Color, scale: f32) -> Color {
+    let channel = |c: u8| (c as f32 * scale).clamp(0.0, 255.0) as u8;
+    Color::new(
+        channel(color.r),
+        channel(color.g),
+        channel(color.b),
+        color.a,
+    )
+}
+
+// Unit vector towards the sun, rising along +X and setting along -X
+pub fn sun_direction(hour: f32, sunrise: f32, tilt: f32) -> Vector3 {
+    let angle = (hour - sunrise) / HOURS_PER_DAY * 2.0 * PI;
+    let height = angle.sin();
+    Vector3::new(angle.cos(), height * tilt.cos(), height * tilt.sin())
+}
+
+// Keyframes interpolated at the hour, wrapping around midnight
+pub fn sample_keyframes(keyframes: &[DayKeyframe], hour: f32) -> DayKeyframe {
+    let hour = hour.rem_euclid(HOURS_PER_DAY);
+    // Last keyframe at or before the hour, before the first one it's the last of the day before
+    let index = keyframes
+        .iter()
+        .rposition(|keyframe| keyframe.hour <= hour)
+        .unwrap_or(keyframes.len() - 1);
+    let from = &keyframes[index];
+    let to = &keyframes[(index + 1) % keyframes.len()];
+
+    let span = (to.hour - from.hour).rem_euclid(HOURS_PER_DAY);
+    let t = if span > 0.0 {
+        (hour - from.hour).rem_euclid(HOURS_PER_DAY) / span
+    } else {
+        0.0
+    };
+
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    let lerp_color = |a: Color, b: Color| {
+        let channel = |a: u8, b: u8| lerp(a as f32, b as f32).round() as u8;
+        Color::new(
+            channel(a.r, b.r),
+            channel(a.g, b.g),
+            channel(a.b, b.b),
+            channel(a.a, b.a),
+        )
+    };
+
+    DayKeyframe {
+        hour,
+        light_color: lerp_color(from.light_color, to.light_color),
+        ambient: lerp(from.ambient, to.ambient),
+        sky: SkyGradient {
+            top: lerp_color(from.sky.top, to.sky.top),
+            horizon: lerp_color(from.sky.horizon, to.sky.horizon),
+            bottom: lerp_color(from.sky.bottom, to.sky.bottom),
+        },
+        fog_density: lerp(from.fog_density, to.fog_density),
+    }
+}
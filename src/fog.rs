@@ -0,0 +1,127 @@
+use crate::constants::*;
+use crate::noise::*;
+use crate::shader::*;
+use raylib::prelude::*;
+
+// Noise texture borrows the unused roughness map slot, like the retro palette
+pub const FOG_NOISE_MAP: MaterialMapIndex = MaterialMapIndex::MATERIAL_MAP_ROUGHNESS;
+pub const FOG_NOISE_LOC: ShaderLocationIndex = ShaderLocationIndex::SHADER_LOC_MAP_ROUGHNESS;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FogMode {
+    // Noise sampled along the view ray, fog looks like a volume
+    Volumetric = 0,
+    // One noise lookup per pixel
+    Simple = 1,
+}
+
+// Web builds get the cheaper fog
+pub const FOG_MODE: FogMode = if GLSL_VERSION == 330 {
+    FogMode::Volumetric
+} else {
+    FogMode::Simple
+};
+
+pub struct FogSettings {
+    // Wind velocity on the ground plane in world units per second
+    pub wind: Vector2,
+    // World units one noise texture repeat covers
+    pub noise_scale: f32,
+    // 0.0 is uniform fog, 1.0 goes from clear to double density
+    pub noise_strength: f32,
+    // How fast fog thins out above base_height, 0.0 disables it
+    pub height_falloff: f32,
+    pub base_height: f32,
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        Self {
+            wind: Vector2::new(0.6, 0.25),
+            noise_scale: 12.0,
+            noise_strength: 0.6,
+            height_falloff: 0.25,
+            base_height: -1.0,
+        }
+    }
+}
+
+// Spatially varying fog drifting with the wind. Every lit material needs
+// the noise texture attached, shaders get the uniforms from apply.
+pub struct AnimatedFog {
+    pub settings: FogSettings,
+    pub enabled: bool,
+    pub texture: Texture2D,
+    scroll: Vector2,
+}
+
+impl AnimatedFog {
+    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread, settings: FogSettings) -> Self {
+        let noise = Noise::new(0xF06);
+        let fractal = Fractal {
+            frequency: 1.0 / 16.0,
+            ..Default::default()
+        };
+        // Tiled, the shader repeats it across the world
+        let texture = bake_noise_texture(
+            rl,
+            thread,
+            64,
+            64,
+            (Color::BLACK, Color::WHITE),
+            seamless(64.0, 64.0, |x, y| noise.fbm2(&fractal, x, y)),
+        );
+        texture.set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_BILINEAR);
+
+        Self {
+            settings,
+            enabled: true,
+            texture,
+            scroll: Vector2::zero(),
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        // Scroll is in texture repeats, wrapping keeps float precision
+        let step = self.settings.wind * (delta_time / self.settings.noise_scale);
+        self.scroll = Vector2::new(
+            (self.scroll.x + step.x).rem_euclid(1.0),
+            (self.scroll.y + step.y).rem_euclid(1.0),
+        );
+    }
+
+    pub fn attach(&self, material: &mut WeakMaterial) {
+        material.maps_mut()[FOG_NOISE_MAP as usize].texture = *self.texture.as_ref();
+    }
+
+    // Disabled fog is uniform again
+    pub fn apply(&self, shader: &mut LightingShader) {
+        let settings = &self.settings;
+        if self.enabled {
+            shader.set_fog_noise(
+                self.scroll,
+                settings.noise_scale,
+                settings.noise_strength,
+                settings.height_falloff,
+                settings.base_height,
+            );
+        } else {
+            shader.set_fog_noise(self.scroll, settings.noise_scale, 0.0, 0.0, 0.0);
+        }
+    }
+
+    // Lowest density a view ray from the camera to anything below scene_top
+    // can get, culling and LOD distances must use it so nothing pops in where
+    // the fog is thin. Fog is thinnest at the highest end of the ray.
+    pub fn min_density(&self, fog_density: f32, camera_height: f32, scene_top: f32) -> f32 {
+        if !self.enabled {
+            return fog_density;
+        }
+
+        let settings = &self.settings;
+        let height = (camera_height.max(scene_top) - settings.base_height).max(0.0);
+        fog_density
+            * (1.0 - settings.noise_strength).max(0.0)
+            * (-height * settings.height_falloff).exp()
+    }
+}
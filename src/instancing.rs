@@ -0,0 +1,31 @@
+use crate::shader::*;
+use raylib::prelude::*;
+
+// Shader define enabling the instanced variant of the lighting shader
+pub const INSTANCING_DEFINE: &str = "INSTANCING";
+
+// Draws the same mesh once per transform in a single draw call,
+// material has to use a shader loaded with INSTANCING_DEFINE
+pub fn draw_mesh_instanced(
+    _d: &mut impl RaylibDraw3D,
+    mesh: &impl RaylibMesh,
+    material: &WeakMaterial,
+    transforms: &[Matrix],
+) {
+    if transforms.is_empty() {
+        return;
+    }
+
+    upload_material_emission(material);
+
+    // NOTE: raylib-rs draw_mesh_instanced takes the mesh by value and unloads it,
+    // so raylib is called directly. Matrix has the same layout as ffi::Matrix
+    unsafe {
+        ffi::DrawMeshInstanced(
+            *mesh.as_ref(),
+            *material.as_ref(),
+            transforms.as_ptr() as *const ffi::Matrix,
+            transforms.len() as i32,
+        );
+    }
+}
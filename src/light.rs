@@ -1,6 +1,6 @@
 use raylib::prelude::*;
 
-const MAX_LIGHTS: u32 = 4;
+pub const MAX_LIGHTS: u32 = 4;
 
 #[repr(i32)]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -17,6 +17,7 @@ impl Default for LightType {
 
 #[derive(Debug, Default, Clone)]
 pub struct Light {
+    pub index: i32,
     pub enabled: bool,
     pub light_type: LightType,
     pub position: Vector3,
@@ -29,6 +30,44 @@ pub struct Light {
     pub color_loc: i32,
 }
 
+// Uniform locations of one light slot of a shader
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LightLocations {
+    pub enabled: i32,
+    pub light_type: i32,
+    pub position: i32,
+    pub target: i32,
+    pub color: i32,
+}
+
+impl LightLocations {
+    pub fn new(shader: &Shader, index: i32) -> Self {
+        let enabled_name = format!("lights[{}].enabled", index);
+        let type_name = format!("lights[{}].type", index);
+        let pos_name = format!("lights[{}].position", index);
+        let target_name = format!("lights[{}].target", index);
+        let color_name = format!("lights[{}].color", index);
+
+        // Set location name [x] depending on light index
+        Self {
+            enabled: shader.get_shader_location(&enabled_name),
+            light_type: shader.get_shader_location(&type_name),
+            position: shader.get_shader_location(&pos_name),
+            target: shader.get_shader_location(&target_name),
+            color: shader.get_shader_location(&color_name),
+        }
+    }
+
+    // Points the light at these locations
+    pub fn bind(&self, light: &mut Light) {
+        light.enabled_loc = self.enabled;
+        light.type_loc = self.light_type;
+        light.pos_loc = self.position;
+        light.target_loc = self.target;
+        light.color_loc = self.color;
+    }
+}
+
 static mut LIGHTS_COUNT: i32 = 0;
 
 // Defines a light and get locations from PBR shader
@@ -48,19 +87,8 @@ pub fn create_light(
         light.target = targ.clone();
         light.color = color.clone();
 
-        let lights_count = unsafe { LIGHTS_COUNT };
-        let enabled_name = format!("lights[{}].enabled", lights_count);
-        let type_name = format!("lights[{}].type", lights_count);
-        let pos_name = format!("lights[{}].position", lights_count);
-        let target_name = format!("lights[{}].target", lights_count);
-        let color_name = format!("lights[{}].color", lights_count);
-
-        // Set location name [x] depending on lights count
-        light.enabled_loc = shader.get_shader_location(&enabled_name);
-        light.type_loc = shader.get_shader_location(&type_name);
-        light.pos_loc = shader.get_shader_location(&pos_name);
-        light.target_loc = shader.get_shader_location(&target_name);
-        light.color_loc = shader.get_shader_location(&color_name);
+        light.index = unsafe { LIGHTS_COUNT };
+        set_light_locations(&mut light, shader);
 
         update_light_values(shader, light.clone());
         unsafe {
@@ -71,6 +99,10 @@ pub fn create_light(
     return light;
 }
 
+fn set_light_locations(light: &mut Light, shader: &Shader) {
+    LightLocations::new(shader, light.index).bind(light);
+}
+
 pub fn update_light_values(shader: &mut Shader, light: Light) {
     // Send to shader light enabled state and type
     shader.set_shader_value(light.enabled_loc, light.enabled as i32);
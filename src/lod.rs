@@ -0,0 +1,115 @@
+use crate::culling::*;
+use raylib::prelude::*;
+
+// Mesh variants of one object ordered from the most detailed,
+// distances[i] is where level i switches to level i + 1
+pub struct Lod {
+    pub meshes: Vec<Mesh>,
+    pub distances: Vec<f32>,
+    // Extra distance an object has to move past a switch point before
+    // the level changes back, prevents popping at the boundary
+    pub hysteresis: f32,
+    // Opt in to replacing the distances with ones fitted to the fog
+    pub fog_fit: bool,
+}
+
+impl Lod {
+    pub fn new(meshes: Vec<Mesh>, distances: Vec<f32>, hysteresis: f32) -> Self {
+        assert!(!meshes.is_empty());
+        assert_eq!(distances.len(), meshes.len() - 1);
+
+        Self {
+            meshes,
+            distances,
+            hysteresis,
+            fog_fit: false,
+        }
+    }
+
+    // Spreads switch distances evenly over the visible fog range,
+    // so detail drops together with the fog factor. Without fog_fit
+    // the distances given to new are kept.
+    pub fn fit_to_fog(&mut self, fog_density: f32) {
+        if self.fog_fit {
+            self.distances = fog_lod_distances(fog_density, self.meshes.len());
+        }
+    }
+
+    // Updates the level of one object, each object keeps its own level
+    pub fn select(&self, level: &mut usize, distance: f32) -> &Mesh {
+        *level = select_lod_level(&self.distances, *level, distance, self.hysteresis);
+        &self.meshes[*level]
+    }
+}
+
+pub fn select_lod_level(
+    distances: &[f32],
+    current: usize,
+    distance: f32,
+    hysteresis: f32,
+) -> usize {
+    let mut level = current.min(distances.len());
+
+    while level < distances.len() && distance > distances[level] + hysteresis {
+        level += 1;
+    }
+    while level > 0 && distance < distances[level - 1] - hysteresis {
+        level -= 1;
+    }
+
+    level
+}
+
+// Switch distances where the fog factor in fog.fs drops by 1/levels each step
+pub fn fog_lod_distances(fog_density: f32, levels: usize) -> Vec<f32> {
+    (1..levels)
+        .map(|i| {
+            let fog_factor = 1.0 - i as f32 / levels as f32;
+            fog_cull_distance(fog_density, fog_factor)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DISTANCES: [f32; 3] = [10.0, 20.0, 30.0];
+    const HYSTERESIS: f32 = 1.0;
+
+    #[test]
+    fn distance_inside_hysteresis_keeps_level() {
+        assert_eq!(select_lod_level(&DISTANCES, 0, 10.5, HYSTERESIS), 0);
+        assert_eq!(select_lod_level(&DISTANCES, 1, 10.5, HYSTERESIS), 1);
+        assert_eq!(select_lod_level(&DISTANCES, 1, 9.5, HYSTERESIS), 1);
+        assert_eq!(select_lod_level(&DISTANCES, 2, 19.5, HYSTERESIS), 2);
+    }
+
+    #[test]
+    fn distance_past_hysteresis_changes_level() {
+        assert_eq!(select_lod_level(&DISTANCES, 0, 11.5, HYSTERESIS), 1);
+        assert_eq!(select_lod_level(&DISTANCES, 1, 8.5, HYSTERESIS), 0);
+        assert_eq!(select_lod_level(&DISTANCES, 1, 21.5, HYSTERESIS), 2);
+        assert_eq!(select_lod_level(&DISTANCES, 2, 18.5, HYSTERESIS), 1);
+    }
+
+    #[test]
+    fn large_jumps_go_straight_to_the_level() {
+        assert_eq!(select_lod_level(&DISTANCES, 0, 100.0, HYSTERESIS), 3);
+        assert_eq!(select_lod_level(&DISTANCES, 3, 0.0, HYSTERESIS), 0);
+        assert_eq!(select_lod_level(&DISTANCES, 0, 25.0, HYSTERESIS), 2);
+    }
+
+    #[test]
+    fn current_level_past_the_last_is_clamped() {
+        assert_eq!(select_lod_level(&DISTANCES, 10, 100.0, HYSTERESIS), 3);
+        assert_eq!(select_lod_level(&DISTANCES, 10, 25.0, HYSTERESIS), 2);
+    }
+
+    #[test]
+    fn fog_distances_increase() {
+        let distances = fog_lod_distances(0.15, 3);
+        assert_eq!(distances.len(), 2);
+        assert!(distances[0] < distances[1]);
+    }
+}
@@ -1,15 +1,68 @@
 use raylib::prelude::*;
 //use raylib::core::audio::{ Sound, RaylibAudio };
 
+mod billboard;
+mod bloom;
+mod camera;
 mod constants;
+mod culling;
+mod day_night;
+mod fog;
+mod instancing;
 mod light;
+mod lod;
+mod model_import;
+mod noise;
+mod parallax;
+mod particles;
+mod picking;
+mod preprocessor;
+mod raylib_alloc;
+mod retro;
+mod scene_graph;
+mod shader;
+mod shake;
+mod skeletal;
+mod sky;
+mod sprite_animation;
 mod structs;
+mod terrain;
 mod text;
+mod tone_map;
+mod toon;
+mod tilemap;
+mod weather;
 
+use billboard::*;
+use bloom::*;
+use camera::*;
 use constants::*;
+use culling::*;
+use day_night::*;
+use fog::*;
+use instancing::*;
 use light::*;
+use lod::*;
+use model_import::*;
+use noise::*;
+use parallax::*;
+use particles::*;
+use picking::*;
+use preprocessor::*;
+use retro::*;
+use scene_graph::*;
+use shader::*;
+use shake::*;
+use skeletal::*;
+use sky::*;
+use sprite_animation::*;
 use structs::*;
+use terrain::*;
 use text::*;
+use tone_map::*;
+use toon::*;
+use tilemap::*;
+use weather::*;
 
 fn main() {
     let (mut rl, thread) = raylib::init()
@@ -57,46 +110,154 @@ fn main() {
         [raylib::consts::MaterialMapIndex::MATERIAL_MAP_ALBEDO as usize]
         .texture = *texture.as_ref();
 
-    // Prepearing right shaders
-    let vertex_shader: &str;
-    let fractal_shader: &str;
-
-    if GLSL_VERSION == 330 {
-        vertex_shader = VERTEX_SHADER_GLSL330;
-        fractal_shader = FRACTAL_SHADER_GLSL330;
-    } else {
-        vertex_shader = VERTEX_SHADER_GLSL100;
-        fractal_shader = FRACTAL_SHADER_GLSL100;
-    }
+    // HDR target keeps specular highlights and emission above 1.0 for bloom
+    let mut render_target: RenderTexture2D =
+        load_render_texture_hdr(&mut rl, &thread, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32);
+    // Scene shaders need to know the 8 bit fallback before they are loaded
+    set_scene_srgb(!is_hdr(&render_target));
 
     // Load shader and set up some uniforms
-    let mut shader = rl.load_shader_from_memory(&thread, Some(vertex_shader), Some(fractal_shader));
-    shader.locs_mut()[raylib::consts::ShaderLocationIndex::SHADER_LOC_MATRIX_MODEL as usize] =
-        shader.get_shader_location("matModel");
-    shader.locs_mut()[raylib::consts::ShaderLocationIndex::SHADER_LOC_VECTOR_VIEW as usize] =
-        shader.get_shader_location("viewPos");
+    let mut lighting = LightingShader::load(&mut rl, &thread, &[]);
+    let mut retro = LightingShader::load(&mut rl, &thread, &[RETRO_DEFINE]);
+    let mut instanced = LightingShader::load(&mut rl, &thread, &[INSTANCING_DEFINE]);
+    let mut cutout = LightingShader::load(&mut rl, &thread, &[ALPHA_CUTOUT_DEFINE]);
+    let mut splatted = LightingShader::load(&mut rl, &thread, &[TERRAIN_DEFINE]);
+    let mut toon = LightingShader::load(&mut rl, &thread, &[TOON_DEFINE]);
+    let mut rim_lit = LightingShader::load(&mut rl, &thread, &[RIM_DEFINE]);
+    setup_toon_shader(&mut toon.shader);
+    setup_rim_shader(&mut rim_lit.shader, &RimSettings::default());
+    let toon_ramp = ToonRamp::new(&mut rl, &thread, &DEFAULT_TOON_BANDS);
+
+    let img = Image::load_image_from_mem(".png", TEXTURE_TEXEL_CHECKER).unwrap();
+    let retro_palette = RetroPalette::from_image(&mut rl, &thread, &img);
+    drop(img);
+    setup_retro_shader(&mut retro.shader, &RetroSettings::default());
 
-    // Ambient light level
-    let ambient_loc = shader.get_shader_location("ambient");
-    shader.set_shader_value(ambient_loc, Vector4::new(0.2, 0.2, 0.2, 0.2));
+    // Fog color always follows the sky horizon
+    let mut gradient_sky = Sky::gradient(&mut rl, &thread, SkyGradient::default());
+    let img = Image::gen_image_checked(6 * 64, 64, 6 * 8, 8, Color::SKYBLUE, Color::LIGHTGRAY);
+    let checker_sky = Sky::cubemap(
+        &mut rl,
+        &thread,
+        &img,
+        CubemapLayout::CUBEMAP_LAYOUT_LINE_HORIZONTAL,
+    );
+    drop(img);
+    let mut is_checker_sky = false;
 
     let mut fog_density = 0.15;
-    let fog_density_loc = shader.get_shader_location("fogDensity");
-    shader.set_shader_value(fog_density_loc, fog_density);
+    for shader in [
+        &mut lighting,
+        &mut retro,
+        &mut instanced,
+        &mut cutout,
+        &mut splatted,
+        &mut toon,
+        &mut rim_lit,
+    ] {
+        shader.set_fog_density(fog_density);
+    }
 
     // NOTE: All models share the same shader
-    model_a.materials_mut()[0].shader = *shader.as_ref();
-    model_b.materials_mut()[0].shader = *shader.as_ref();
-    model_c.materials_mut()[0].shader = *shader.as_ref();
+    model_a.materials_mut()[0].shader = *lighting.shader.as_ref();
+    model_b.materials_mut()[0].shader = *lighting.shader.as_ref();
+    model_c.materials_mut()[0].shader = *lighting.shader.as_ref();
+    let mut is_retro_sphere = false;
+
+    // Row of tori is drawn with a single instanced draw call
+    let mut tori_material = rl.load_material_default(&thread);
+    tori_material.shader = *instanced.shader.as_ref();
+    tori_material.maps_mut()[raylib::consts::MaterialMapIndex::MATERIAL_MAP_ALBEDO as usize]
+        .texture = *texture.as_ref();
+    let mut tori_transforms: Vec<Matrix> = Vec::with_capacity(20);
+
+    // Fog drifting with the wind, every lit material samples its noise texture
+    let mut fog = AnimatedFog::new(&mut rl, &thread, FogSettings::default());
+    fog.attach(&mut model_a.materials_mut()[0]);
+    fog.attach(&mut model_b.materials_mut()[0]);
+    fog.attach(&mut model_c.materials_mut()[0]);
+    fog.attach(&mut tori_material);
+
+    // Outlines drawn around the imported models
+    let mut outline = Outline::new(&mut rl, &thread, &OutlineSettings::default(), &fog);
+    let mut is_outlined = false;
 
-    // Using just 1 point lights
-    create_light(
+    // Lower tessellation variants for far away, fogged out models
+    let mut torus_lod = Lod::new(
+        vec![
+            Mesh::gen_mesh_torus(&thread, 0.4, 1.0, 16, 32),
+            Mesh::gen_mesh_torus(&thread, 0.4, 1.0, 10, 20),
+            Mesh::gen_mesh_torus(&thread, 0.4, 1.0, 6, 12),
+        ],
+        vec![6.0, 12.0],
+        0.5,
+    );
+    // Tori switch detail with the fog, the sphere at fixed distances
+    torus_lod.fog_fit = true;
+    let mut sphere_lod = Lod::new(
+        vec![
+            Mesh::gen_mesh_sphere(&thread, 0.5, 32, 32),
+            Mesh::gen_mesh_sphere(&thread, 0.5, 16, 16),
+            Mesh::gen_mesh_sphere(&thread, 0.5, 8, 8),
+        ],
+        vec![6.0, 12.0],
+        0.5,
+    );
+    let mut torus_level = 0;
+    let mut sphere_level = 0;
+
+    // Model space bounds used for culling
+    let bounds_a = BoundingSphere::from_mesh(&model_a.meshes()[0]);
+    let bounds_b = BoundingSphere::from_mesh(&model_b.meshes()[0]);
+    let bounds_c = BoundingSphere::from_mesh(&model_c.meshes()[0]);
+    let box_a = model_a.meshes()[0].get_mesh_bounding_box();
+    let box_b = model_b.meshes()[0].get_mesh_bounding_box();
+    let box_c = model_c.meshes()[0].get_mesh_bounding_box();
+    // Clicked node with its model space box
+    let mut selected: Option<PickHit<(NodeId, BoundingBox)>> = None;
+    // Left button held after a click that selected something doesn't steer the ball
+    let mut is_picking = false;
+
+    // Glowing cube, strength above 1.0 makes it bloom
+    set_material_emission(&mut model_b.materials_mut()[0], Color::ORANGE, 1.5);
+
+    // Models, the row of tori and the lamp share one transform hierarchy
+    let mut scene = SceneGraph::default();
+    let torus_spin = Vector3::new(-1.5, 0.0, 0.72);
+    let torus_node = scene.add(LocalTransform::default(), None);
+    scene.node_mut(torus_node).angular_velocity = torus_spin;
+    let cube_node = scene.add(LocalTransform::from_translation(rvec3(-2.6, 0, 0)), None);
+    let sphere_node = scene.add(LocalTransform::from_translation(rvec3(2.6, 0, 0)), None);
+
+    // Each torus of the row spins in place
+    let tori_row = scene.add(LocalTransform::default(), None);
+    let tori_nodes: Vec<NodeId> = (-20..20)
+        .step_by(2)
+        .map(|i| {
+            let transform = LocalTransform::from_translation(Vector3::new(i as f32, 0.0, 2.0));
+            let node = scene.add(transform, Some(tori_row));
+            scene.node_mut(node).angular_velocity = torus_spin;
+            node
+        })
+        .collect();
+    let mut tori_levels: Vec<usize> = vec![0; tori_nodes.len()];
+    let mut tori_batches: Vec<Vec<Matrix>> =
+        vec![Vec::with_capacity(tori_nodes.len()); torus_lod.meshes.len()];
+
+    // Single point light hanging from a pivot, an angular velocity
+    // on the pivot would circle it around the models
+    let lamp_pivot = scene.add(LocalTransform::default(), None);
+    let lamp = scene.add(LocalTransform::from_translation(rvec3(0, 2, 6)), Some(lamp_pivot));
+    scene.node_mut(lamp).light = Some(create_light(
         LightType::LightPoint,
-        rvec3(0, 2, 6),
+        Vector3::zero(),
         Vector3::zero(),
         Color::WHITE,
-        &mut shader,
-    );
+        &mut lighting.shader,
+    ));
+
+    // Sun, ambient level, sky and fog follow the time of day
+    let mut day_night = DayNight::new(DayNightSettings::default(), 10.0, &mut lighting.shader);
 
     let mut cam_background_3d = Camera3D::perspective(
         Vector3::new(0f32, 10f32, 10f32),
@@ -105,35 +266,349 @@ fn main() {
         45f32,
     );
 
+    // Rail circles the scene, follow mode chases the 2D ball projected onto the ground
+    let rail = RailCamera::new(
+        (0..8)
+            .map(|i| {
+                let angle = i as f32 / 8.0 * std::f32::consts::TAU;
+                Vector3::new(
+                    angle.cos() * 12.0,
+                    3.0 + (angle * 2.0).sin() * 2.0,
+                    angle.sin() * 12.0,
+                )
+            })
+            .collect(),
+        Vector3::zero(),
+        0.5,
+    );
+    let mut camera_controller = CameraController::new(rail);
+
+    let mut shake = CameraShake::new(ShakeSettings::default());
+
     rl.set_target_fps(60u32); // Set our game to run at 60 frames-per-second
     rl.set_window_min_size(SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32);
 
-    let mut render_target: RenderTexture2D = rl
-        .load_render_texture(&thread, SCREEN_WIDTH as u32, SCREEN_HEIGHT as u32)
-        .unwrap();
+    let mut bloom = Bloom::new(
+        &mut rl,
+        &thread,
+        SCREEN_WIDTH as u32,
+        SCREEN_HEIGHT as u32,
+        BloomSettings::default(),
+    );
+    // Without float targets the scene clips at 1.0 and is sRGB encoded,
+    // only the brightest colors bloom
+    if !is_hdr(&render_target) {
+        bloom.settings.threshold = 0.9;
+    }
+
+    let mut tone_mapping = ToneMapping::new(
+        &mut rl,
+        &thread,
+        SCREEN_WIDTH as u32,
+        SCREEN_HEIGHT as u32,
+        ToneMapper::Aces,
+        1.0,
+    );
+    tone_mapping.set_retro_palette(retro_palette, &RetroSettings::default());
 
     let mut value: i32 = rl.get_random_value(-100i32..100i32); // not right documentation
     let mut frame_count = 0;
-    let mut is_colliding: bool;
+    let mut is_colliding: bool = false;
 
-    let img = Image::load_image_from_mem(".png", TEXTURE_TREE_LEFT).unwrap();
-    let mut ball = Ball::new(&mut rl, &thread, &img);
-    drop(img);
+    // Ball sprite turns to the side it moves to
+    let mut ball_sprite = SpriteSheet::default();
+    for data in [&TEXTURE_TREE_LEFT[..], &TEXTURE_TREE_RIGHT[..]] {
+        let img = Image::load_image_from_mem(".png", data).unwrap();
+        ball_sprite.add_texture(rl.load_texture_from_image(&thread, &img).unwrap());
+    }
+    let ball_animation = SpriteAnimation::new(vec![
+        Clip::new("idle", vec![0, 1], 0.8, PlaybackMode::PingPong),
+        Clip::new("left", vec![0], 1.0, PlaybackMode::Loop),
+        Clip::new("right", vec![1], 1.0, PlaybackMode::Loop),
+        Clip::new("hit", vec![0, 1, 0, 1], 0.08, PlaybackMode::Once),
+    ]);
+    let mut ball_facing = DirectionClips::horizontal("left", "right");
+    ball_facing.idle = Some("idle".to_string());
+    let mut ball = Ball::new(ball_sprite, ball_animation, ball_facing);
+
+    // Level made in Tiled, tileset images are looked up in the embedded assets
+    let tilemap = Tilemap::load(&mut rl, &thread, LEVEL_MAP, |image| match image {
+        "ground.png" => Some(&TEXTURE_GROUND[..]),
+        _ => None,
+    })
+    .unwrap();
+    if let Some(spawn) = tilemap.spawn("ball") {
+        ball.position = spawn;
+    }
+
+    // 2D layer is a world larger than the screen, camera follows the ball
+    let world_bounds = tilemap.bounds();
+    let mut camera_2d = FollowCamera2D::new(
+        Vector2::new(SCREEN_WIDTH, SCREEN_HEIGHT),
+        world_bounds,
+        ball.position,
+    );
 
     let mut bouncing_ball = BouncingBall {
-        position: Vector2::new(SCREEN_WIDTH / 2f32, SCREEN_HEIGHT / 2f32),
+        position: Vector2::new(WORLD_WIDTH / 2f32, WORLD_HEIGHT / 2f32),
         velocity: Vector2::new(200f32, 200f32),
         radius: 5f32,
         color: Color::BLUE,
     };
+    if let Some(spawn) = tilemap.spawn("bouncing_ball") {
+        bouncing_ball.position = spawn;
+    }
+
+    // Sparks for explosions and collisions, 2D ones are in pixels
+    let mut sparks_2d = Emitter::new(
+        EmitterSettings {
+            lifetime: (0.3, 0.7),
+            speed: (40f32, 140f32),
+            gravity: Vector3::new(0f32, 160f32, 0f32),
+            drag: 2f32,
+            start_color: Color::YELLOW,
+            end_color: Color::RED.alpha(0f32),
+            start_size: 4f32,
+            end_size: 1f32,
+            blend_mode: BlendMode::BLEND_ADDITIVE,
+            ..Default::default()
+        },
+        256,
+        1,
+    );
+    let mut sparks_3d = Emitter::new(
+        EmitterSettings {
+            lifetime: (0.6, 1.2),
+            speed: (2f32, 5f32),
+            gravity: Vector3::new(0f32, -4f32, 0f32),
+            drag: 1.5,
+            start_color: Color::ORANGE,
+            end_color: Color::RED.alpha(0f32),
+            start_size: 0.6,
+            end_size: 0.1,
+            blend_mode: BlendMode::BLEND_ADDITIVE,
+            ..Default::default()
+        },
+        256,
+        2,
+    );
+    // Trail left behind the moving ball
+    let mut trail_2d = Emitter::new(
+        EmitterSettings {
+            rate: 40f32,
+            lifetime: (0.3, 0.5),
+            speed: (2f32, 8f32),
+            start_color: ball.color.alpha(0.6),
+            end_color: Color::ORANGE.alpha(0f32),
+            start_size: 5f32,
+            end_size: 1f32,
+            ..Default::default()
+        },
+        64,
+        3,
+    );
+    // Dust floating around the models in the fog
+    let mut dust_3d = Emitter::new(
+        EmitterSettings {
+            rate: 12f32,
+            lifetime: (3f32, 6f32),
+            speed: (0.05, 0.2),
+            spawn_radius: 6f32,
+            start_color: Color::RAYWHITE.alpha(0.5),
+            end_color: Color::RAYWHITE.alpha(0f32),
+            start_size: 0.15,
+            end_size: 0.05,
+            drag: 0.5,
+            turbulence: 0.6,
+            turbulence_frequency: 0.5,
+            ..Default::default()
+        },
+        128,
+        4,
+    );
+    dust_3d.position = Vector3::new(0f32, 1.5, 0f32);
+    dust_3d.emitting = true;
+
+    let img = Image::gen_image_gradient_radial(16, 16, 0f32, Color::WHITE, Color::BLANK);
+    let particle_texture = rl.load_texture_from_image(&thread, &img).unwrap();
+    let unlit_shader = load_unlit_shader(&mut rl, &thread);
+    drop(img);
+
+    // Weather follows the script in the scene file until it's changed by hand
+    let scene_file = SceneFile::parse(SCENE).unwrap();
+    let mut weather = Weather::new(scene_file.weather);
+    let mut rain = Precipitation::rain(3000);
+    let mut snow = Precipitation::snow(1500);
+
+    // Hills around a flat clearing for the models
+    let img = Image::load_image_from_mem(".png", TEXTURE_HEIGHTMAP).unwrap();
+    let mut terrain = Terrain::from_image(
+        &mut rl,
+        &thread,
+        &img,
+        TerrainSettings {
+            center: Vector3::new(0f32, -1.5, 0f32),
+            ..Default::default()
+        },
+        &splatted.shader,
+    );
+    drop(img);
 
+    // Ground in valleys, grass higher up and rock on steep slopes
     let img = Image::load_image_from_mem(".png", TEXTURE_GROUND).unwrap();
     let texture_ground = rl.load_texture_from_image(&thread, &img).unwrap();
     drop(img);
+    let noise = Noise::new(1337);
+    let grass_noise = Fractal {
+        frequency: 1f32 / 8f32,
+        ..Default::default()
+    };
+    let texture_grass = bake_noise_texture(
+        &mut rl,
+        &thread,
+        64,
+        64,
+        (Color::new(40, 70, 30, 255), Color::new(110, 170, 80, 255)),
+        seamless(64f32, 64f32, |x, y| noise.domain_warp2(&grass_noise, x, y, 4f32)),
+    );
+    let rock_noise = Fractal {
+        frequency: 1f32 / 16f32,
+        ..Default::default()
+    };
+    let texture_rock = bake_noise_texture(
+        &mut rl,
+        &thread,
+        64,
+        64,
+        (Color::DARKGRAY, Color::LIGHTGRAY),
+        seamless(64f32, 64f32, |x, y| noise.ridged2(&rock_noise, x, y) * 2f32 - 1f32),
+    );
+    terrain.set_splat_textures([&texture_ground, &texture_grass, &texture_rock]);
 
-    let img = Image::load_image_from_mem(".png", TEXTURE_TREE_RIGHT).unwrap();
-    let texture_tree = rl.load_texture_from_image(&thread, &img).unwrap();
-    drop(img);
+    // Imported models are embedded like the other assets and loaded by path.
+    // A glTF or OBJ file given on the command line replaces the column.
+    embed_file("assets/column.gltf", MODEL_COLUMN);
+    embed_file("assets/crate.obj", MODEL_CRATE);
+    let mut column_path = std::env::args()
+        .nth(1)
+        .unwrap_or("assets/column.gltf".to_string());
+    let mut column = match load_model(&mut rl, &thread, &column_path, &lighting.shader, &fog) {
+        Ok(model) => model,
+        Err(error) => {
+            let message = format!("{}, loading the column instead", error);
+            rl.trace_log(TraceLogLevel::LOG_WARNING, &message);
+            column_path = "assets/column.gltf".to_string();
+            load_model(&mut rl, &thread, &column_path, &lighting.shader, &fog).unwrap()
+        }
+    };
+    let mut column_animator = Animator::new(
+        rl.load_model_animations(&thread, &column_path)
+            .unwrap_or_default(),
+    );
+    let mut is_column_bent = false;
+    let mut lighting_model = LightingModel::Phong;
+    let mut crate_model =
+        load_model(&mut rl, &thread, "assets/crate.obj", &lighting.shader, &fog).unwrap();
+    crate_model.materials_mut()[0].maps_mut()
+        [raylib::consts::MaterialMapIndex::MATERIAL_MAP_ALBEDO as usize]
+        .texture = *texture.as_ref();
+    let column_node = scene.add(
+        LocalTransform::from_translation(Vector3::new(4.5, terrain.height_at(4.5, -3.0), -3.0)),
+        None,
+    );
+    let crate_node = scene.add(
+        LocalTransform::from_translation(Vector3::new(
+            -4.5,
+            terrain.height_at(-4.5, -3.0) + 0.5,
+            -3.0,
+        )),
+        None,
+    );
+
+    // Forest scattered around the orbital scene, with mist drifting between the trees
+    let mut billboards = BillboardRenderer::new(&thread);
+    let mut tree_textures = Vec::new();
+    for data in [&TEXTURE_TREE_LEFT[..], &TEXTURE_TREE_RIGHT[..]] {
+        let img = Image::load_image_from_mem(".png", data).unwrap();
+        tree_textures.push(rl.load_texture_from_image(&thread, &img).unwrap());
+    }
+    for texture in &tree_textures {
+        billboards.add_material(&mut rl, &thread, &cutout.shader, texture, Color::WHITE);
+    }
+    let mist_material = billboards.add_material(
+        &mut rl,
+        &thread,
+        &lighting.shader,
+        &particle_texture,
+        Color::WHITE.alpha(0.35),
+    );
+    fog.attach(&mut terrain.material);
+    for material in billboards.materials.iter_mut() {
+        fog.attach(material);
+    }
+    // Trees grow in clumps where the noise is high, on a jittered grid so they don't overlap
+    let forest_noise = Fractal {
+        frequency: 1f32 / 6f32,
+        octaves: 3,
+        ..Default::default()
+    };
+    for gx in -9i32..=9 {
+        for gz in -9i32..=9 {
+            let (cell_x, cell_z) = (gx as f32 * 2f32, gz as f32 * 2f32);
+            let x = cell_x + noise.simplex2(cell_x * 3.1, cell_z * 3.1) * 0.8;
+            let z = cell_z + noise.simplex2(cell_z * 3.1 + 50f32, cell_x * 3.1) * 0.8;
+            let distance = Vector2::new(x, z).length();
+            if !(7f32..18f32).contains(&distance) || noise.fbm2(&forest_noise, x, z) < 0.1 {
+                continue;
+            }
+
+            let height = 3f32 + (noise.perlin2(x * 0.7, z * 0.7) * 0.5 + 0.5) * 1.5;
+            billboards.billboards.push(Billboard {
+                // Slightly sunk, so trunks don't float on slopes
+                position: Vector3::new(x, terrain.height_at(x, z) - 0.1, z),
+                size: Vector2::new(height, height),
+                material: (gx + gz).unsigned_abs() as usize % tree_textures.len(),
+                mode: BillboardMode::Cylindrical,
+                translucent: false,
+            });
+        }
+    }
+    for i in 0..8 {
+        let angle = (i as f32 * 45f32).to_radians();
+        let (x, z) = (angle.cos() * 6f32, angle.sin() * 6f32);
+        billboards.billboards.push(Billboard {
+            position: Vector3::new(x, terrain.height_at(x, z) - 0.5, z),
+            size: Vector2::new(6f32, 3f32),
+            material: mist_material,
+            mode: BillboardMode::Spherical,
+            translucent: true,
+        });
+    }
+    // Tree tops and hills are the highest culled objects, models stay close to the ground
+    let scene_top = billboards
+        .billboards
+        .iter()
+        .map(|billboard| billboard.bounds())
+        .map(|bounds| bounds.center.y + bounds.radius)
+        .chain(terrain.heights.iter().copied())
+        .fold(f32::MIN, f32::max);
+
+    // Tree rows behind the 2D world, the far row is faded and drifts slowly
+    let mut parallax = Parallax::default();
+    for (data, scroll, y, alpha) in [
+        (&TEXTURE_TREE_LEFT[..], 0.2f32, 40f32, 0.4f32),
+        (&TEXTURE_TREE_RIGHT[..], 0.5f32, 110f32, 1f32),
+    ] {
+        let img = Image::load_image_from_mem(".png", data).unwrap();
+        let mut layer =
+            ParallaxLayer::new(rl.load_texture_from_image(&thread, &img).unwrap(), scroll);
+        layer.position.y = y;
+        layer.repeat_x = true;
+        layer.spacing.x = 24f32;
+        layer.tint = Color::WHITE.alpha(alpha);
+        parallax.layers.push(layer);
+    }
+    parallax.layers[0].velocity.x = -4f32;
 
     // Comment regarding this is right on the start of gameloop
     // // needed to manage fullscreen properly
@@ -190,7 +665,15 @@ fn main() {
         // play sound
         if rl.is_key_pressed(KeyboardKey::KEY_Z) && !sound.is_playing(){
             sound.play();
+            shake.add_trauma(0.6);
+
+            // Explosion goes off at the ball in both views
+            sparks_2d.position = Vector3::new(ball.position.x, ball.position.y, 0f32);
+            sparks_2d.burst(32);
+            sparks_3d.position = ball.world_position();
+            sparks_3d.burst(48);
         }
+        shake.update(delta_time);
 
 
         /* Use of simple toggle_borderless_window gives good result on windows and linux, so no reason to use toggle_fullscreen*/
@@ -231,9 +714,96 @@ fn main() {
             rl.toggle_borderless_windowed();
         }
 
+        // Mouse in render target pixels, the target is scaled and centered in the window
+        let mouse_target = screen_to_target(
+            rl.get_mouse_position(),
+            (rl.get_screen_width(), rl.get_screen_height()),
+            (SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32),
+        );
+
         // shader controls
         {
-            rl.update_camera(&mut cam_background_3d, CameraMode::CAMERA_ORBITAL); // Update camera, seting an orbital camera mode
+            if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_V) {
+                let mode = camera_controller.mode.next();
+                camera_controller.set_mode(mode, &cam_background_3d);
+            }
+
+            scene.update(delta_time);
+
+            // Crossfade to the next clip of the imported model
+            if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_X)
+                && !column_animator.clips.is_empty()
+            {
+                let next = (column_animator.current_clip() + 1) % column_animator.clips.len();
+                column_animator.crossfade(next, true, 0.4);
+            }
+            // Imported models switch between the lighting shader variants
+            if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_L) {
+                lighting_model = lighting_model.next();
+                let shader = match lighting_model {
+                    LightingModel::Phong => &lighting.shader,
+                    LightingModel::Toon => &toon.shader,
+                    LightingModel::Rim => &rim_lit.shader,
+                };
+                for material in column.materials_mut() {
+                    apply_lighting_model(material, shader, &toon_ramp);
+                }
+                for material in crate_model.materials_mut() {
+                    apply_lighting_model(material, shader, &toon_ramp);
+                }
+            }
+            if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_O) {
+                is_outlined = !is_outlined;
+            }
+
+            // Half of the bend clip mixed into whatever plays
+            if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_M) {
+                if let Some(bend) = column_animator.clip_index("bend") {
+                    is_column_bent = !is_column_bent;
+                    column_animator.set_blend(bend, if is_column_bent { 0.5 } else { 0.0 });
+                }
+            }
+            column_animator.update(delta_time);
+            column_animator.apply(&mut column);
+            tori_transforms.clear();
+            tori_transforms.extend(tori_nodes.iter().map(|node| scene.world(*node)));
+
+            // Click a model or a torus of the row to select it, empty space clears the selection
+            if let (true, Some(mouse)) = (
+                rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT),
+                mouse_target,
+            ) {
+                let view_size = Vector2::new(SCREEN_WIDTH, SCREEN_HEIGHT);
+                let ray = camera_ray(&cam_background_3d, mouse, view_size);
+                let models = [
+                    (torus_node, &model_a, box_a),
+                    (cube_node, &model_b, box_b),
+                    (sphere_node, &model_c, box_c),
+                ]
+                .map(|(node, model, bounds)| {
+                    let transform = *model.transform() * scene.world(node);
+                    Pickable::new((node, bounds), &model.meshes()[0], bounds, transform)
+                });
+                let tori = tori_nodes.iter().map(|node| {
+                    let transform = scene.world(*node);
+                    Pickable::new((*node, box_a), &model_a.meshes()[0], box_a, transform)
+                });
+                selected = pick(ray, models.into_iter().chain(tori));
+                is_picking = selected.is_some();
+            }
+
+            let obstacles = [
+                bounds_a.transformed(&scene.world(torus_node)),
+                bounds_b.transformed(&scene.world(cube_node)),
+                bounds_c.transformed(&scene.world(sphere_node)),
+            ];
+            camera_controller.update(
+                &mut cam_background_3d,
+                &CameraInput::read(&rl),
+                ball.world_position(),
+                &obstacles,
+                delta_time,
+            );
 
             if rl.is_key_down(raylib::consts::KeyboardKey::KEY_F) {
                 fog_density += 0.001;
@@ -249,16 +819,90 @@ fn main() {
                 }
             }
 
-            shader.set_shader_value(fog_density_loc, fog_density);
+            // Retro render mode is selected per material
+            if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_R) {
+                is_retro_sphere = !is_retro_sphere;
+                if is_retro_sphere {
+                    apply_retro_material(&mut model_c.materials_mut()[0], &retro.shader);
+                } else {
+                    model_c.materials_mut()[0].shader = *lighting.shader.as_ref();
+                }
+            }
+
+            if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_B) {
+                bloom.enabled = !bloom.enabled;
+            }
+
+            if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_G) {
+                fog.enabled = !fog.enabled;
+            }
+            fog.update(delta_time);
+
+            if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_N) {
+                day_night.next_speed();
+            }
+            if rl.is_key_down(raylib::consts::KeyboardKey::KEY_RIGHT_BRACKET) {
+                day_night.scrub(3.0 * delta_time);
+            }
+            if rl.is_key_down(raylib::consts::KeyboardKey::KEY_LEFT_BRACKET) {
+                day_night.scrub(-3.0 * delta_time);
+            }
+            if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_H) {
+                let next = if *weather.target() == WeatherState::CLEAR {
+                    WeatherState::RAIN
+                } else if *weather.target() == WeatherState::RAIN {
+                    WeatherState::SNOW
+                } else {
+                    WeatherState::CLEAR
+                };
+                weather.set(next, 5.0);
+            }
+            weather.update(delta_time);
+            day_night.light_scale = weather.current().light;
+            day_night.ambient_scale = weather.current().ambient;
+            day_night.fog_scale = weather.current().fog_density;
+            day_night.update(delta_time);
+            gradient_sky.set_gradient(day_night.sky());
+            let scene_fog_density = day_night.fog_density(fog_density);
+
+            // Thinnest fog decides how far models stay detailed
+            let camera_height = cam_background_3d.position.y;
+            let min_fog_density = fog.min_density(scene_fog_density, camera_height, scene_top);
+            torus_lod.fit_to_fog(min_fog_density);
+            sphere_lod.fit_to_fog(min_fog_density);
+
+            if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_T) {
+                tone_mapping.tone_mapper = tone_mapping.tone_mapper.next();
+            }
+
+            // Checker cubemap makes sky seams easy to spot
+            if rl.is_key_pressed(raylib::consts::KeyboardKey::KEY_K) {
+                is_checker_sky = !is_checker_sky;
+            }
 
-            // Rotate the torus
-            model_a.set_transform(&(*model_a.transform() * Matrix::rotate_x(-0.025)));
-            model_a.set_transform(&(*model_a.transform() * Matrix::rotate_z(0.012)));
+            let horizon = if is_checker_sky {
+                checker_sky.horizon
+            } else {
+                gradient_sky.horizon
+            };
 
-            // Update the light shader with the camera view position
-            let loc = shader.locs_mut()
-                [raylib::consts::ShaderLocationIndex::SHADER_LOC_VECTOR_VIEW as usize];
-            shader.set_shader_value(loc, cam_background_3d.position);
+            for shader in [
+                &mut lighting,
+                &mut retro,
+                &mut instanced,
+                &mut cutout,
+                &mut splatted,
+                &mut toon,
+                &mut rim_lit,
+                &mut outline.shader,
+            ] {
+                shader.set_fog_density(scene_fog_density);
+                shader.set_fog_color(horizon);
+                shader.set_view_position(cam_background_3d.position);
+                fog.apply(shader);
+                day_night.apply(shader);
+                scene.apply_lights(shader);
+            }
         }
 
         /* --- UPDATE --- */
@@ -272,6 +916,14 @@ fn main() {
                 bouncing_ball.radius,
             ) {
                 bouncing_ball.velocity *= -1f32;
+                if !is_colliding {
+                    shake.add_trauma(0.4);
+                    ball.animation.play("hit");
+
+                    let contact = ball.position.lerp(bouncing_ball.position, 0.5);
+                    sparks_2d.position = Vector3::new(contact.x, contact.y, 0f32);
+                    sparks_2d.burst(24);
+                }
                 is_colliding = true;
             } else {
                 is_colliding = false;
@@ -280,16 +932,17 @@ fn main() {
 
         // Bouncing Ball
         {
-            bouncing_ball.position += bouncing_ball.velocity * delta_time;
+            // Each axis is moved separately, so the ball bounces off walls on the hit side
+            let step = bouncing_ball.velocity * delta_time;
 
-            if bouncing_ball.position.x >= SCREEN_WIDTH - bouncing_ball.radius
-                || bouncing_ball.position.x <= bouncing_ball.radius
-            {
+            bouncing_ball.position.x += step.x;
+            if tilemap.collides_circle(bouncing_ball.position, bouncing_ball.radius) {
+                bouncing_ball.position.x -= step.x;
                 bouncing_ball.velocity.x *= -1f32;
             }
-            if bouncing_ball.position.y >= SCREEN_HEIGHT - bouncing_ball.radius
-                || bouncing_ball.position.y <= bouncing_ball.radius
-            {
+            bouncing_ball.position.y += step.y;
+            if tilemap.collides_circle(bouncing_ball.position, bouncing_ball.radius) {
+                bouncing_ball.position.y -= step.y;
                 bouncing_ball.velocity.y *= -1f32;
             }
         }
@@ -303,10 +956,20 @@ fn main() {
             }
         }
 
+        let previous_position = ball.position;
+
         // Handle ball movement [Mouse]
         {
-            if rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
-                ball.position = ball.position.lerp(rl.get_mouse_position(), 0.025f32);
+            if !rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+                is_picking = false;
+            }
+            if let (true, false, Some(mouse)) = (
+                rl.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT),
+                is_picking,
+                mouse_target,
+            ) {
+                let mouse_world = rl.get_screen_to_world2D(mouse, camera_2d.camera);
+                ball.position = ball.position.lerp(mouse_world, 0.025f32);
                 // you may want to add a desired location to which object will move
             }
         }
@@ -328,48 +991,196 @@ fn main() {
             }
             direction.normalize();
 
-            // saving direction into struct to use when drawing sprite
-            // struct may not have own direction, if will not be used further
+            // direction picks the sprite animation clip
             ball.direction = direction;
 
             ball.position += ball.direction * ball.speed * delta_time;
         }
 
+        // Collision layers of the map are the walls of the 2D layer,
+        // movement is undone per axis so the ball slides along them
+        if tilemap.collides_circle(ball.position, ball.radius) {
+            let target = ball.position;
+            ball.position = previous_position;
+
+            ball.position.x = target.x;
+            if tilemap.collides_circle(ball.position, ball.radius) {
+                ball.position.x = previous_position.x;
+            }
+            ball.position.y = target.y;
+            if tilemap.collides_circle(ball.position, ball.radius) {
+                ball.position.y = previous_position.y;
+            }
+        }
+
+        ball.update_animation(delta_time);
+        camera_2d.update(ball.position, ball.direction, delta_time);
+        parallax.update(delta_time);
+
+        trail_2d.position = Vector3::new(ball.position.x, ball.position.y, 0f32);
+        trail_2d.emitting = ball.direction != Vector2::zero();
+        for emitter in [&mut sparks_2d, &mut sparks_3d, &mut trail_2d, &mut dust_3d] {
+            emitter.update(delta_time);
+        }
+        let current_weather = weather.current();
+        rain.update(delta_time, current_weather.rain, current_weather.wind);
+        snow.update(delta_time, current_weather.snow, current_weather.wind);
+
         /* --- DRAW --- */
+
+        let sky = if is_checker_sky {
+            &checker_sky
+        } else {
+            &gradient_sky
+        };
+
+        let shaken_camera_3d = shake.apply_3d(&cam_background_3d, SCREEN_HEIGHT);
+        let shaken_camera_2d = shake.apply_2d(&camera_2d.camera);
+        let view_rect =
+            view_rect_2d(&shaken_camera_2d, Vector2::new(SCREEN_WIDTH, SCREEN_HEIGHT));
+
+        // Skip models outside of the view or hidden by fog, the shaken camera is the one drawn with
+        let mut culling = Culling::new(
+            &shaken_camera_3d,
+            SCREEN_WIDTH / SCREEN_HEIGHT,
+            fog.min_density(
+                day_night.fog_density(fog_density),
+                shaken_camera_3d.position.y,
+                scene_top,
+            ),
+        );
+
         let mut d = rl.begin_drawing(&thread);
         d.clear_background(Color::BLACK);
         {
             let mut d = d.begin_texture_mode(&thread, &mut render_target);
             d.clear_background(Color::GRAY);
             {
-                let mut d = d.begin_mode3D(cam_background_3d);
+                let mut d = d.begin_mode3D(shaken_camera_3d);
+
+                sky.draw(&mut d);
 
                 // Draw the three models
-                d.draw_model(&model_a, Vector3::zero(), 1.0, Color::WHITE);
-                d.draw_model(&model_b, rvec3(-2.6, 0, 0), 1.0, Color::WHITE);
-                d.draw_model(&model_c, rvec3(2.6, 0, 0), 1.0, Color::WHITE);
+                for (model, bounds, node, lod) in [
+                    (
+                        &model_a,
+                        &bounds_a,
+                        torus_node,
+                        Some((&torus_lod, &mut torus_level)),
+                    ),
+                    (&model_b, &bounds_b, cube_node, None),
+                    (
+                        &model_c,
+                        &bounds_c,
+                        sphere_node,
+                        Some((&sphere_lod, &mut sphere_level)),
+                    ),
+                ] {
+                    let transform = *model.transform() * scene.world(node);
+                    let bounds = bounds.transformed(&transform);
+                    if !culling.test(&bounds) {
+                        continue;
+                    }
 
-                for i in (-20..20).step_by(2) {
-                    d.draw_model(&model_a, rvec3(i, 0, 2), 1.0, Color::WHITE);
+                    match lod {
+                        Some((lod, level)) => {
+                            let distance = bounds.center.distance_to(shaken_camera_3d.position);
+                            let mesh = lod.select(level, distance);
+                            let material = &model.materials()[0];
+                            if material.shader.id == retro.shader.id {
+                                draw_retro_mesh(&mut d, mesh, material, transform);
+                            } else {
+                                draw_lit_mesh(&mut d, mesh, material, transform);
+                            }
+                        }
+                        None => draw_lit_model(&mut d, model, scene.world(node)),
+                    }
+                }
+
+                draw_lit_model(&mut d, &column, scene.world(column_node));
+                draw_lit_model(&mut d, &crate_model, scene.world(crate_node));
+                if is_outlined {
+                    outline.draw_model(&mut d, &column, scene.world(column_node));
+                    outline.draw_model(&mut d, &crate_model, scene.world(crate_node));
+                }
+
+                // Tori are batched per LOD level, one instanced draw call each
+                for batch in tori_batches.iter_mut() {
+                    batch.clear();
+                }
+                for (transform, level) in tori_transforms.iter().zip(tori_levels.iter_mut()) {
+                    let bounds = bounds_a.transformed(transform);
+                    if culling.test(&bounds) {
+                        let distance = bounds.center.distance_to(shaken_camera_3d.position);
+                        torus_lod.select(level, distance);
+                        tori_batches[*level].push(*transform);
+                    }
+                }
+                for (mesh, batch) in torus_lod.meshes.iter().zip(&tori_batches) {
+                    draw_mesh_instanced(&mut d, mesh, &tori_material, batch);
+                }
+
+                terrain.draw(&mut d, &mut culling);
+
+                // After opaque models, so translucent billboards blend over them
+                billboards.draw(&mut d, &shaken_camera_3d, &mut culling);
+
+                // Debug shapes are unlit, the shader converts their colors to linear
+                let mut d = d.begin_shader_mode(&unlit_shader);
+
+                // Selection box follows the node, the normal stays where it was clicked
+                if let Some(hit) = &selected {
+                    let (node, bounds) = hit.object;
+                    let bounds = transform_box(&bounds, &scene.world(node));
+                    d.draw_bounding_box(bounds, Color::YELLOW);
+                    d.draw_line_3D(hit.point, hit.point + hit.normal * 0.5, Color::YELLOW);
+                }
+
+                if camera_controller.mode == ControllerMode::Follow {
+                    d.draw_sphere_wires(ball.world_position(), 0.5, 8, 8, ball.color);
                 }
             }
 
             // ENTER 3D MODE
             {
-                let mut d3d = d.begin_mode3D(&cam_background_3d);
-                d3d.draw_grid(128i32, 4f32);
+                let mut d3d = d.begin_mode3D(shaken_camera_3d);
+                // Particles go last, they don't write depth
+                let particle_fog = Some((sky.horizon, day_night.fog_density(fog_density)));
+                let (camera, texture) = (&shaken_camera_3d, &particle_texture);
+                for emitter in [&dust_3d, &sparks_3d] {
+                    emitter.draw_3d(&mut d3d, camera, texture, &unlit_shader, particle_fog);
+                }
+                for precipitation in [&rain, &snow] {
+                    precipitation.draw(&mut d3d, camera, texture, &unlit_shader, particle_fog);
+                }
             }
-            // draw texture
-            d.draw_texture_rec(
-                &texture_ground,
-                Rectangle::new(0f32, 0f32, SCREEN_WIDTH / 4f32, SCREEN_HEIGHT / 4f32),
-                Vector2::new(0f32, 0f32),
-                Color::WHITE,
+        }
+
+        // Post processing, bloom and tone mapping work on the linear HDR scene
+        let scene_target: &RenderTexture2D = if bloom.enabled {
+            bloom.apply(&mut d, &thread, &render_target);
+            &bloom.output
+        } else {
+            &render_target
+        };
+        tone_mapping.apply(&mut d, &thread, scene_target);
+
+        // 2D layers and text are in display colors, drawn after tone mapping
+        {
+            let mut d = d.begin_texture_mode(&thread, &mut tone_mapping.output);
+            parallax.draw(
+                &mut d,
+                &shaken_camera_2d,
+                Vector2::new(SCREEN_WIDTH, SCREEN_HEIGHT),
             );
-            d.draw_texture_v(&texture_tree, Vector2::new(0f32, 0f32), Color::WHITE);
 
-            // draw bouncing ball
+            // draw texture
             {
+                let mut d = d.begin_mode2D(shaken_camera_2d);
+                tilemap.draw(&mut d, view_rect);
+                d.draw_rectangle_lines_ex(world_bounds, 2f32, Color::DARKGRAY);
+
+                // draw bouncing ball
                 d.draw_circle_v(
                     bouncing_ball.position,
                     bouncing_ball.radius,
@@ -402,6 +1213,9 @@ fn main() {
 
             // Handle ball drawing
             {
+                let mut d = d.begin_mode2D(shaken_camera_2d);
+                trail_2d.draw_2d(&mut d, None);
+                sparks_2d.draw_2d(&mut d, None);
                 ball.draw(&mut d);
                 d.draw_circle_v(ball.position, ball.radius + 2f32, ball.color);
                 d.draw_circle_v(ball.position, ball.radius, Color::WHITE);
@@ -423,35 +1237,69 @@ fn main() {
                 12i32,
                 Color::RAYWHITE,
             );
-        }
-        {
-            // Screen scaling
-            let mut scaling: i32 = 1i32;
-
-            let scale_y = d.get_screen_height() / SCREEN_HEIGHT as i32;
-            let scale_x = d.get_screen_width() / SCREEN_WIDTH as i32;
-
-            if scale_x != scaling && scale_y != scaling {
-                if render_target.texture.width * scaling <= d.get_screen_width()
-                    && render_target.texture.height * scaling <= d.get_screen_height()
-                {
-                    if scale_x >= scale_y {
-                        scaling = scale_y as i32;
-                    } else {
-                        scaling = scale_x as i32;
-                    }
-                }
+            d.draw_text(
+                tone_mapping.tone_mapper.name(),
+                12i32,
+                48i32,
+                12i32,
+                Color::RAYWHITE,
+            );
+            if let Some(hit) = &selected {
+                let (node, _) = hit.object;
+                let normal = hit.normal;
+                d.draw_text(
+                    &format!(
+                        "node {} at {:.2} normal {:.2} {:.2} {:.2}",
+                        node, hit.distance, normal.x, normal.y, normal.z
+                    ),
+                    12i32,
+                    96i32,
+                    12i32,
+                    Color::RAYWHITE,
+                );
             }
-
-            let screen_center: Vector2 = Vector2::new(
-                d.get_screen_width() as f32 / 2f32,
-                d.get_screen_height() as f32 / 2f32,
+            let clip = column_animator.clips.get(column_animator.current_clip());
+            d.draw_text(
+                &format!(
+                    "{} {}",
+                    lighting_model.name(),
+                    clip.map(clip_name).unwrap_or_default()
+                ),
+                12i32,
+                108i32,
+                12i32,
+                Color::RAYWHITE,
             );
-
-            let render_target_position: Vector2 = Vector2::new(
-                screen_center.x - (render_target.texture.width * scaling) as f32 / 2f32,
-                screen_center.y - (render_target.texture.height * scaling) as f32 / 2f32,
+            d.draw_text(
+                &format!("{} x{}", day_night.clock(), day_night.speed),
+                12i32,
+                84i32,
+                12i32,
+                Color::RAYWHITE,
             );
+            d.draw_text(
+                camera_controller.mode.name(),
+                12i32,
+                72i32,
+                12i32,
+                Color::RAYWHITE,
+            );
+            d.draw_text(
+                &format!("drawn {} culled {}", culling.stats.drawn, culling.stats.culled),
+                12i32,
+                60i32,
+                12i32,
+                Color::RAYWHITE,
+            );
+        }
+
+        {
+            let render_target = &tone_mapping.output;
+            // Screen scaling, picking maps the mouse back the same way
+            let screen_size = (d.get_screen_width(), d.get_screen_height());
+            let target_size = (render_target.texture.width, render_target.texture.height);
+            let scaling = render_target_scaling(screen_size, target_size);
+            let render_target_position = render_target_origin(screen_size, target_size, scaling);
 
             d.draw_texture_pro(
                 render_target.texture(),
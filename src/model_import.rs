@@ -0,0 +1,121 @@
+use crate::fog::*;
+use crate::raylib_alloc::*;
+use crate::shader::*;
+use raylib::prelude::*;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, c_uchar, CStr};
+use std::path::Path;
+use std::sync::Mutex;
+
+// Model formats raylib loads meshes and materials from
+pub const MODEL_EXTENSIONS: [&str; 3] = ["gltf", "glb", "obj"];
+
+static EMBEDDED_FILES: Mutex<Option<HashMap<String, &'static [u8]>>> = Mutex::new(None);
+
+// Makes embedded data loadable by path with every raylib loader, including
+// buffers and textures a glTF file references. Other paths are read from disk.
+// OBJ material libraries are always read from disk by tinyobj.
+pub fn embed_file(path: &str, data: &'static [u8]) {
+    let mut files = EMBEDDED_FILES.lock().unwrap();
+    if files.is_none() {
+        unsafe {
+            ffi::SetLoadFileDataCallback(Some(load_file_data));
+            ffi::SetLoadFileTextCallback(Some(load_file_text));
+        }
+    }
+    files
+        .get_or_insert_with(HashMap::new)
+        .insert(path.to_string(), data);
+}
+
+fn file_contents(path: &str) -> Option<Cow<'static, [u8]>> {
+    let embedded = EMBEDDED_FILES
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|files| files.get(path).copied());
+
+    match embedded {
+        Some(data) => Some(Cow::Borrowed(data)),
+        None => std::fs::read(path).ok().map(Cow::Owned),
+    }
+}
+
+// raylib frees loaded file data itself, so copies are allocated by raylib
+unsafe extern "C" fn load_file_data(
+    file_name: *const c_char,
+    data_size: *mut c_int,
+) -> *mut c_uchar {
+    *data_size = 0;
+    let path = CStr::from_ptr(file_name).to_string_lossy();
+
+    match file_contents(&path) {
+        Some(data) => {
+            *data_size = data.len() as c_int;
+            raylib_copy(&data)
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn load_file_text(file_name: *const c_char) -> *mut c_char {
+    let path = CStr::from_ptr(file_name).to_string_lossy();
+
+    match file_contents(&path) {
+        Some(data) => {
+            let mut text = data.into_owned();
+            text.push(0);
+            raylib_copy(&text) as *mut c_char
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
+// Loads a glTF 2.0 or OBJ model, embedded or from disk, and moves its
+// materials onto the lighting shader
+pub fn load_model(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    path: &str,
+    shader: &Shader,
+    fog: &AnimatedFog,
+) -> Result<Model, String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    if !MODEL_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(format!("unsupported model format {}", path));
+    }
+
+    // raylib never returns a model without meshes, it falls back to a cube
+    // when loading fails, so check the file first. A file that can be read
+    // but not parsed still shows up as that cube.
+    if file_contents(path).is_none() {
+        return Err(format!("could not read model {}", path));
+    }
+
+    let mut model = rl.load_model(thread, path)?;
+
+    for material in model.materials_mut() {
+        import_material(material, shader, fog);
+    }
+    Ok(model)
+}
+
+// Imported albedo textures and colors stay. Emissive glTF materials glow at
+// their emissive color, the lighting shader needs a strength for that.
+pub fn import_material(material: &mut WeakMaterial, shader: &Shader, fog: &AnimatedFog) {
+    material.shader = *shader.as_ref();
+
+    let emission: Color = material.maps()[MaterialMapIndex::MATERIAL_MAP_EMISSION as usize]
+        .color
+        .into();
+    if emission.r > 0 || emission.g > 0 || emission.b > 0 {
+        set_material_emission(material, emission, 1.0);
+    }
+
+    fog.attach(material);
+}
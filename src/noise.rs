@@ -0,0 +1,428 @@
+use crate::raylib_alloc::*;
+use raylib::prelude::*;
+
+// Only basic float arithmetic is used, no trigonometry or platform math,
+// so the same seed gives the same values on native and wasm builds
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NoiseKind {
+    Perlin,
+    Simplex,
+}
+
+// Settings of fractal noise, every octave has lacunarity times
+// the frequency and gain times the amplitude of the previous one
+#[derive(Clone, Copy, Debug)]
+pub struct Fractal {
+    pub kind: NoiseKind,
+    pub octaves: u32,
+    pub frequency: f32,
+    pub lacunarity: f32,
+    pub gain: f32,
+}
+
+impl Default for Fractal {
+    fn default() -> Self {
+        Self {
+            kind: NoiseKind::Simplex,
+            octaves: 4,
+            frequency: 1.0,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+}
+
+// Seeded gradient noise, all functions return values in about [-1..1]
+pub struct Noise {
+    perm: [u8; 512],
+}
+
+impl Noise {
+    pub fn new(seed: u64) -> Self {
+        let mut table: [u8; 256] = std::array::from_fn(|i| i as u8);
+
+        // Fisher-Yates shuffle driven by splitmix64
+        let mut state = seed;
+        for i in (1..256).rev() {
+            state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^= z >> 31;
+            table.swap(i, (z % (i as u64 + 1)) as usize);
+        }
+
+        Self {
+            perm: std::array::from_fn(|i| table[i & 255]),
+        }
+    }
+
+    fn hash2(&self, x: i32, y: i32) -> u8 {
+        let x = (x & 255) as usize;
+        let y = (y & 255) as usize;
+        self.perm[self.perm[x] as usize + y]
+    }
+
+    fn hash3(&self, x: i32, y: i32, z: i32) -> u8 {
+        let z = (z & 255) as usize;
+        self.perm[self.hash2(x, y) as usize + z]
+    }
+
+    pub fn perlin2(&self, x: f32, y: f32) -> f32 {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (ix, iy) = (x0 as i32, y0 as i32);
+
+        let corner =
+            |cx: i32, cy: i32| grad2(self.hash2(ix + cx, iy + cy), fx - cx as f32, fy - cy as f32);
+        let (u, v) = (fade(fx), fade(fy));
+
+        let bottom = lerp(corner(0, 0), corner(1, 0), u);
+        let top = lerp(corner(0, 1), corner(1, 1), u);
+        lerp(bottom, top, v)
+    }
+
+    pub fn perlin3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+        let (fx, fy, fz) = (x - x0, y - y0, z - z0);
+        let (ix, iy, iz) = (x0 as i32, y0 as i32, z0 as i32);
+
+        let corner = |cx: i32, cy: i32, cz: i32| {
+            grad3(
+                self.hash3(ix + cx, iy + cy, iz + cz),
+                fx - cx as f32,
+                fy - cy as f32,
+                fz - cz as f32,
+            )
+        };
+        let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+
+        let near = lerp(
+            lerp(corner(0, 0, 0), corner(1, 0, 0), u),
+            lerp(corner(0, 1, 0), corner(1, 1, 0), u),
+            v,
+        );
+        let far = lerp(
+            lerp(corner(0, 0, 1), corner(1, 0, 1), u),
+            lerp(corner(0, 1, 1), corner(1, 1, 1), u),
+            v,
+        );
+        lerp(near, far, w)
+    }
+
+    pub fn simplex2(&self, x: f32, y: f32) -> f32 {
+        const F2: f32 = 0.366_025_42; // (sqrt(3) - 1) / 2
+        const G2: f32 = 0.211_324_87; // (3 - sqrt(3)) / 6
+
+        // Skew to find the simplex cell
+        let s = (x + y) * F2;
+        let (i, j) = ((x + s).floor(), (y + s).floor());
+        let t = (i + j) * G2;
+        let (x0, y0) = (x - (i - t), y - (j - t));
+
+        let (i1, j1) = if x0 > y0 { (1, 0) } else { (0, 1) };
+        let (x1, y1) = (x0 - i1 as f32 + G2, y0 - j1 as f32 + G2);
+        let (x2, y2) = (x0 - 1.0 + 2.0 * G2, y0 - 1.0 + 2.0 * G2);
+        let (i, j) = (i as i32, j as i32);
+
+        let corner = |hash: u8, x: f32, y: f32| {
+            let t = 0.5 - x * x - y * y;
+            if t < 0.0 {
+                0.0
+            } else {
+                let t = t * t;
+                t * t * grad2(hash, x, y)
+            }
+        };
+
+        let n = corner(self.hash2(i, j), x0, y0)
+            + corner(self.hash2(i + i1, j + j1), x1, y1)
+            + corner(self.hash2(i + 1, j + 1), x2, y2);
+        n * 70.0
+    }
+
+    pub fn simplex3(&self, x: f32, y: f32, z: f32) -> f32 {
+        const F3: f32 = 1.0 / 3.0;
+        const G3: f32 = 1.0 / 6.0;
+
+        let s = (x + y + z) * F3;
+        let (i, j, k) = ((x + s).floor(), (y + s).floor(), (z + s).floor());
+        let t = (i + j + k) * G3;
+        let (x0, y0, z0) = (x - (i - t), y - (j - t), z - (k - t));
+
+        // Which of the six tetrahedra the point is in
+        let (i1, j1, k1, i2, j2, k2) = if x0 >= y0 {
+            if y0 >= z0 {
+                (1, 0, 0, 1, 1, 0)
+            } else if x0 >= z0 {
+                (1, 0, 0, 1, 0, 1)
+            } else {
+                (0, 0, 1, 1, 0, 1)
+            }
+        } else if y0 < z0 {
+            (0, 0, 1, 0, 1, 1)
+        } else if x0 < z0 {
+            (0, 1, 0, 0, 1, 1)
+        } else {
+            (0, 1, 0, 1, 1, 0)
+        };
+
+        let offset = |a: i32, b: i32, c: i32, g: f32| {
+            (x0 - a as f32 + g, y0 - b as f32 + g, z0 - c as f32 + g)
+        };
+        let (x1, y1, z1) = offset(i1, j1, k1, G3);
+        let (x2, y2, z2) = offset(i2, j2, k2, 2.0 * G3);
+        let (x3, y3, z3) = offset(1, 1, 1, 3.0 * G3);
+        let (i, j, k) = (i as i32, j as i32, k as i32);
+
+        let corner = |hash: u8, x: f32, y: f32, z: f32| {
+            let t = 0.6 - x * x - y * y - z * z;
+            if t < 0.0 {
+                0.0
+            } else {
+                let t = t * t;
+                t * t * grad3(hash, x, y, z)
+            }
+        };
+
+        let n = corner(self.hash3(i, j, k), x0, y0, z0)
+            + corner(self.hash3(i + i1, j + j1, k + k1), x1, y1, z1)
+            + corner(self.hash3(i + i2, j + j2, k + k2), x2, y2, z2)
+            + corner(self.hash3(i + 1, j + 1, k + 1), x3, y3, z3);
+        n * 32.0
+    }
+
+    pub fn sample2(&self, kind: NoiseKind, x: f32, y: f32) -> f32 {
+        match kind {
+            NoiseKind::Perlin => self.perlin2(x, y),
+            NoiseKind::Simplex => self.simplex2(x, y),
+        }
+    }
+
+    pub fn sample3(&self, kind: NoiseKind, x: f32, y: f32, z: f32) -> f32 {
+        match kind {
+            NoiseKind::Perlin => self.perlin3(x, y, z),
+            NoiseKind::Simplex => self.simplex3(x, y, z),
+        }
+    }
+
+    // Fractal Brownian motion, octaves summed and normalized back to [-1..1]
+    pub fn fbm2(&self, fractal: &Fractal, x: f32, y: f32) -> f32 {
+        fractal_sum(fractal, |frequency, octave| {
+            // Octaves are offset, so their lattices don't line up at the origin
+            let shift = octave as f32 * 19.19;
+            self.sample2(fractal.kind, x * frequency + shift, y * frequency - shift)
+        })
+    }
+
+    pub fn fbm3(&self, fractal: &Fractal, x: f32, y: f32, z: f32) -> f32 {
+        fractal_sum(fractal, |frequency, octave| {
+            let shift = octave as f32 * 19.19;
+            self.sample3(
+                fractal.kind,
+                x * frequency + shift,
+                y * frequency - shift,
+                z * frequency + shift,
+            )
+        })
+    }
+
+    // Sharp crests where the noise crosses zero, like mountain ridges, in [0..1]
+    pub fn ridged2(&self, fractal: &Fractal, x: f32, y: f32) -> f32 {
+        let value = fractal_sum(fractal, |frequency, octave| {
+            let shift = octave as f32 * 19.19;
+            let n = 1.0
+                - self
+                    .sample2(fractal.kind, x * frequency + shift, y * frequency)
+                    .abs();
+            n * n * 2.0 - 1.0
+        });
+        value * 0.5 + 0.5
+    }
+
+    // fbm sampled at a position displaced by two other fbm lookups,
+    // strength is the displacement in input units
+    pub fn domain_warp2(&self, fractal: &Fractal, x: f32, y: f32, strength: f32) -> f32 {
+        let warp_x = self.fbm2(fractal, x + 5.2, y + 1.3);
+        let warp_y = self.fbm2(fractal, x - 9.7, y + 2.8);
+        self.fbm2(fractal, x + warp_x * strength, y + warp_y * strength)
+    }
+}
+
+fn fractal_sum(fractal: &Fractal, mut octave: impl FnMut(f32, u32) -> f32) -> f32 {
+    let mut sum = 0.0;
+    let mut amplitude = 1.0;
+    let mut total = 0.0;
+    let mut frequency = fractal.frequency;
+
+    for i in 0..fractal.octaves.max(1) {
+        sum += octave(frequency, i) * amplitude;
+        total += amplitude;
+        amplitude *= fractal.gain;
+        frequency *= fractal.lacunarity;
+    }
+
+    sum / total
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+// One of 8 directions
+fn grad2(hash: u8, x: f32, y: f32) -> f32 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+// One of the 12 cube edge directions, 4 of them repeated to fill 16
+fn grad3(hash: u8, x: f32, y: f32, z: f32) -> f32 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+// Makes a function tile with the given period by blending it with copies
+// shifted by one period, needed for textures that repeat
+pub fn seamless(
+    width: f32,
+    height: f32,
+    noise: impl Fn(f32, f32) -> f32,
+) -> impl Fn(f32, f32) -> f32 {
+    move |x, y| {
+        let (u, v) = (x.rem_euclid(width) / width, y.rem_euclid(height) / height);
+        let (x, y) = (u * width, v * height);
+
+        let top = noise(x, y) * (1.0 - u) + noise(x - width, y) * u;
+        let bottom = noise(x, y - height) * (1.0 - u) + noise(x - width, y - height) * u;
+        let value = top * (1.0 - v) + bottom * v;
+
+        // Blending lowers contrast the most in the middle, where all four copies mix
+        let weight = ((1.0 - u) * (1.0 - u) + u * u) * ((1.0 - v) * (1.0 - v) + v * v);
+        value / weight.sqrt()
+    }
+}
+
+// Image of the function called with pixel coordinates,
+// [-1..1] is mapped to the gradient from low to high
+pub fn bake_noise_image(
+    width: i32,
+    height: i32,
+    (low, high): (Color, Color),
+    noise: impl Fn(f32, f32) -> f32,
+) -> Image {
+    let channel =
+        |low: u8, high: u8, t: f32| (low as f32 + (high as f32 - low as f32) * t).round() as u8;
+
+    let pixels: Vec<u8> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            let t = (noise(x as f32, y as f32) * 0.5 + 0.5).clamp(0.0, 1.0);
+            [
+                channel(low.r, high.r, t),
+                channel(low.g, high.g, t),
+                channel(low.b, high.b, t),
+                channel(low.a, high.a, t),
+            ]
+        })
+        .collect();
+
+    unsafe {
+        Image::from_raw(ffi::Image {
+            data: raylib_copy(&pixels) as *mut std::ffi::c_void,
+            width,
+            height,
+            mipmaps: 1,
+            format: PixelFormat::PIXELFORMAT_UNCOMPRESSED_R8G8B8A8 as i32,
+        })
+    }
+}
+
+pub fn bake_noise_texture(
+    rl: &mut RaylibHandle,
+    thread: &RaylibThread,
+    width: i32,
+    height: i32,
+    gradient: (Color, Color),
+    noise: impl Fn(f32, f32) -> f32,
+) -> Texture2D {
+    let img = bake_noise_image(width, height, gradient, noise);
+    rl.load_texture_from_image(thread, &img).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: u64 = 1234;
+    const POINTS: [(f32, f32, f32); 3] = [(0.5, 0.25, 0.75), (3.7, -1.2, 8.9), (-12.3, 45.6, -7.8)];
+
+    // Stored from a known good build, the values must not change between
+    // platforms or versions or every baked texture and terrain changes too
+    fn assert_golden(values: [f32; 3], golden: [f32; 3]) {
+        for (value, golden) in values.iter().zip(golden) {
+            assert!((value - golden).abs() < 1e-6, "{} != {}", value, golden);
+        }
+    }
+
+    #[test]
+    fn perlin2_golden_values() {
+        let noise = Noise::new(SEED);
+        assert_golden(
+            POINTS.map(|(x, y, _)| noise.perlin2(x, y)),
+            [-0.33618164, -0.026917696, -0.21019727],
+        );
+    }
+
+    #[test]
+    fn simplex3_golden_values() {
+        let noise = Noise::new(SEED);
+        assert_golden(
+            POINTS.map(|(x, y, z)| noise.simplex3(x, y, z)),
+            [-0.40725306, -0.74543536, 0.2614402],
+        );
+    }
+
+    #[test]
+    fn fbm2_golden_values() {
+        let noise = Noise::new(SEED);
+        let fractal = Fractal::default();
+        assert_golden(
+            POINTS.map(|(x, y, _)| noise.fbm2(&fractal, x, y)),
+            [-0.17887944, -0.49833852, -0.042564835],
+        );
+    }
+
+    #[test]
+    fn gradient_noise_is_zero_on_lattice_points() {
+        let noise = Noise::new(SEED);
+        assert_eq!(noise.perlin2(3.0, 4.0), 0.0);
+        assert_eq!(noise.simplex3(0.0, 0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn seeds_give_different_noise() {
+        let (a, b) = (Noise::new(SEED), Noise::new(SEED + 1));
+        assert_ne!(a.perlin2(0.5, 0.25), b.perlin2(0.5, 0.25));
+    }
+}
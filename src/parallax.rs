@@ -0,0 +1,105 @@
+use raylib::prelude::*;
+
+pub struct ParallaxLayer {
+    pub texture: Texture2D,
+    // How much the layer moves with the camera, 0.0 is fixed to the screen
+    // and 1.0 moves like the world
+    pub scroll: Vector2,
+    // Auto scroll in pixels per second, like drifting clouds
+    pub velocity: Vector2,
+    // Screen position of the layer when the camera is at the world origin
+    pub position: Vector2,
+    pub repeat_x: bool,
+    pub repeat_y: bool,
+    // Empty space between repeated copies
+    pub spacing: Vector2,
+    pub tint: Color,
+}
+
+impl ParallaxLayer {
+    pub fn new(texture: Texture2D, scroll: f32) -> Self {
+        Self {
+            texture,
+            scroll: Vector2::new(scroll, scroll),
+            velocity: Vector2::zero(),
+            position: Vector2::zero(),
+            repeat_x: false,
+            repeat_y: false,
+            spacing: Vector2::zero(),
+            tint: Color::WHITE,
+        }
+    }
+}
+
+// Layers are drawn in order, so the farthest layer goes first
+#[derive(Default)]
+pub struct Parallax {
+    pub layers: Vec<ParallaxLayer>,
+    time: f32,
+}
+
+impl Parallax {
+    pub fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+    }
+
+    // Draws in screen space, call outside of 2D mode
+    pub fn draw(&self, d: &mut impl RaylibDraw, camera: &Camera2D, view_size: Vector2) {
+        // World position of the top left corner of the view
+        let view_origin = camera.target - camera.offset * (1.0 / camera.zoom);
+
+        for layer in &self.layers {
+            let size = Vector2::new(layer.texture.width as f32, layer.texture.height as f32);
+            let period = size + layer.spacing;
+            let position = layer.position + layer.velocity * self.time;
+
+            let start = Vector2::new(
+                layer_start(
+                    position.x,
+                    view_origin.x,
+                    layer.scroll.x,
+                    period.x,
+                    layer.repeat_x,
+                ),
+                layer_start(
+                    position.y,
+                    view_origin.y,
+                    layer.scroll.y,
+                    period.y,
+                    layer.repeat_y,
+                ),
+            );
+
+            let count_x = repeat_count(layer.repeat_x, view_size.x, period.x);
+            let count_y = repeat_count(layer.repeat_y, view_size.y, period.y);
+            for y in 0..count_y {
+                for x in 0..count_x {
+                    let tile = start + Vector2::new(x as f32 * period.x, y as f32 * period.y);
+                    d.draw_texture_v(&layer.texture, tile, layer.tint);
+                }
+            }
+        }
+    }
+}
+
+// Screen position of the first copy of a layer along one axis, snapped to whole
+// pixels so layers scrolling at different speeds don't shimmer in the low res target.
+// Repeating layers are wrapped to start just left of the screen edge.
+pub fn layer_start(position: f32, view_origin: f32, scroll: f32, period: f32, repeat: bool) -> f32 {
+    let start = (position - view_origin * scroll).round();
+
+    if repeat && period > 0.0 {
+        start.rem_euclid(period) - period
+    } else {
+        start
+    }
+}
+
+// Copies needed to cover the view, one extra for the wrapped copy left of the screen
+fn repeat_count(repeat: bool, view_size: f32, period: f32) -> i32 {
+    if repeat && period > 0.0 {
+        (view_size / period).ceil() as i32 + 1
+    } else {
+        1
+    }
+}
@@ -0,0 +1,332 @@
+use crate::constants::*;
+use crate::noise::*;
+use crate::preprocessor::*;
+use raylib::prelude::*;
+use std::f32::consts::PI;
+
+// Particles live in 3D space, 2D emitters use x and y in pixels and leave z at zero
+#[derive(Clone, Copy, Default)]
+pub struct Particle {
+    pub position: Vector3,
+    pub velocity: Vector3,
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+pub struct EmitterSettings {
+    // Particles per second while emitting, 0.0 only emits bursts
+    pub rate: f32,
+    // Random range of lifetime in seconds
+    pub lifetime: (f32, f32),
+    // Random range of start speed
+    pub speed: (f32, f32),
+    // Particles fly out in this direction, spread around it
+    pub direction: Vector3,
+    // Half angle of the emission cone in radians, PI emits in all directions
+    pub spread: f32,
+    // Particles spawn inside a sphere of this radius around the emitter
+    pub spawn_radius: f32,
+    pub gravity: Vector3,
+    // Fraction of the velocity lost per second
+    pub drag: f32,
+    // Strength of the noise force field pushing particles around
+    pub turbulence: f32,
+    // Size of the turbulence swirls, in noise periods per unit
+    pub turbulence_frequency: f32,
+    pub start_color: Color,
+    pub end_color: Color,
+    pub start_size: f32,
+    pub end_size: f32,
+    pub blend_mode: BlendMode,
+}
+
+impl Default for EmitterSettings {
+    fn default() -> Self {
+        Self {
+            rate: 0.0,
+            lifetime: (0.5, 1.0),
+            speed: (1.0, 2.0),
+            direction: Vector3::up(),
+            spread: PI,
+            spawn_radius: 0.0,
+            gravity: Vector3::zero(),
+            drag: 0.0,
+            turbulence: 0.0,
+            turbulence_frequency: 1.0,
+            start_color: Color::WHITE,
+            end_color: Color::WHITE.alpha(0.0),
+            start_size: 1.0,
+            end_size: 1.0,
+            blend_mode: BlendMode::BLEND_ALPHA,
+        }
+    }
+}
+
+// Emitter with pooled storage, the pool never grows past its capacity
+// and dead particles are swapped behind the alive ones
+pub struct Emitter {
+    pub settings: EmitterSettings,
+    pub position: Vector3,
+    // Continuous emission, bursts work either way
+    pub emitting: bool,
+    pool: Vec<Particle>,
+    alive: usize,
+    // Fraction of a particle left over from the last update
+    pending: f32,
+    seed: u32,
+    noise: Noise,
+    time: f32,
+}
+
+impl Emitter {
+    // Emitters with different seeds spawn different particles
+    pub fn new(settings: EmitterSettings, capacity: usize, seed: u32) -> Self {
+        Self {
+            settings,
+            position: Vector3::zero(),
+            emitting: false,
+            pool: vec![Particle::default(); capacity],
+            alive: 0,
+            pending: 0.0,
+            // xorshift gets stuck at zero, the constant also keeps small seeds
+            // from starting with a few tiny numbers
+            seed: (seed ^ 0x2545_F491).max(1),
+            // Turbulence differs between emitters too
+            noise: Noise::new(seed as u64),
+            time: 0.0,
+        }
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.pool[..self.alive]
+    }
+
+    // Spawns count particles at once, particles past the capacity are dropped
+    pub fn burst(&mut self, count: usize) {
+        for _ in 0..count {
+            if self.alive == self.pool.len() {
+                break;
+            }
+
+            let particle = self.spawn();
+            self.pool[self.alive] = particle;
+            self.alive += 1;
+        }
+    }
+
+    fn spawn(&mut self) -> Particle {
+        let settings = &self.settings;
+        let (lifetime, speed) = (settings.lifetime, settings.speed);
+        let (direction, spread, spawn_radius) =
+            (settings.direction, settings.spread, settings.spawn_radius);
+
+        let lifetime = lerp(lifetime.0, lifetime.1, self.random());
+        let speed = lerp(speed.0, speed.1, self.random());
+        let direction = cone_direction(direction, spread, self.random(), self.random());
+        let offset = cone_direction(Vector3::up(), PI, self.random(), self.random())
+            * (spawn_radius * self.random().cbrt());
+
+        Particle {
+            position: self.position + offset,
+            velocity: direction * speed,
+            age: 0.0,
+            lifetime,
+        }
+    }
+
+    // xorshift, emitters don't need the raylib handle for random numbers
+    fn random(&mut self) -> f32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed as f32 / u32::MAX as f32
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        let settings = &self.settings;
+        let drag = (1.0 - settings.drag * delta_time).max(0.0);
+        self.time += delta_time;
+
+        let mut i = 0;
+        while i < self.alive {
+            let particle = &mut self.pool[i];
+            particle.age += delta_time;
+            if particle.age >= particle.lifetime {
+                self.alive -= 1;
+                self.pool.swap(i, self.alive);
+                continue;
+            }
+
+            let mut force = settings.gravity;
+            if settings.turbulence > 0.0 {
+                force += turbulence(&self.noise, particle.position, settings, self.time);
+            }
+
+            particle.velocity = (particle.velocity + force * delta_time) * drag;
+            particle.position += particle.velocity * delta_time;
+            i += 1;
+        }
+
+        if self.emitting {
+            self.pending += self.settings.rate * delta_time;
+            let count = self.pending.floor();
+            self.pending -= count;
+            self.burst(count as usize);
+        } else {
+            self.pending = 0.0;
+        }
+    }
+
+    // Color and size of a particle at its age
+    pub fn appearance(&self, particle: &Particle) -> (Color, f32) {
+        let t = (particle.age / particle.lifetime).clamp(0.0, 1.0);
+        let settings = &self.settings;
+
+        (
+            lerp_color(settings.start_color, settings.end_color, t),
+            lerp(settings.start_size, settings.end_size, t),
+        )
+    }
+
+    // Draws in world space of the 2D camera, size is the diameter in pixels.
+    // Without a texture particles are circles.
+    pub fn draw_2d(&self, d: &mut impl RaylibDraw, texture: Option<&Texture2D>) {
+        let mut d = d.begin_blend_mode(self.settings.blend_mode);
+
+        for particle in self.particles() {
+            let (color, size) = self.appearance(particle);
+            let center = Vector2::new(particle.position.x, particle.position.y);
+
+            match texture {
+                Some(texture) => d.draw_texture_pro(
+                    texture,
+                    Rectangle::new(0.0, 0.0, texture.width as f32, texture.height as f32),
+                    Rectangle::new(center.x, center.y, size, size),
+                    Vector2::new(size * 0.5, size * 0.5),
+                    0.0,
+                    color,
+                ),
+                None => d.draw_circle_v(center, size * 0.5, color),
+            }
+        }
+    }
+
+    // Camera facing billboards, size is in world units. Billboards don't go
+    // through the lighting shader, fog is matched on the CPU and the unlit
+    // shader converts to linear color.
+    pub fn draw_3d(
+        &self,
+        d: &mut (impl RaylibDraw + RaylibDraw3D),
+        camera: &Camera3D,
+        texture: &Texture2D,
+        unlit_shader: &Shader,
+        fog: Option<(Color, f32)>,
+    ) {
+        let mut d = d.begin_shader_mode(unlit_shader);
+        let mut d = d.begin_blend_mode(self.settings.blend_mode);
+
+        // Particles overlap each other, so they must not hide the ones behind them
+        unsafe {
+            ffi::rlDrawRenderBatchActive();
+            ffi::rlDisableDepthMask();
+        }
+
+        for particle in self.particles() {
+            let (mut color, size) = self.appearance(particle);
+            if let Some((fog_color, fog_density)) = fog {
+                let distance = particle.position.distance_to(camera.position);
+                let fog_factor = fog_factor(distance, fog_density);
+                color = apply_fog(color, fog_color, fog_factor);
+            }
+
+            d.draw_billboard(*camera, texture, particle.position, size, color);
+        }
+
+        unsafe {
+            ffi::rlDrawRenderBatchActive();
+            ffi::rlEnableDepthMask();
+        }
+    }
+}
+
+// Force field changing smoothly in space and time, each axis reads
+// the noise far away from the others so they are independent
+fn turbulence(noise: &Noise, position: Vector3, settings: &EmitterSettings, time: f32) -> Vector3 {
+    let fractal = Fractal {
+        octaves: 2,
+        frequency: settings.turbulence_frequency,
+        ..Default::default()
+    };
+    let t = time * 0.25;
+
+    Vector3::new(
+        noise.fbm3(&fractal, position.x + t, position.y, position.z),
+        noise.fbm3(&fractal, position.x, position.y + t + 31.4, position.z),
+        noise.fbm3(&fractal, position.x, position.y, position.z + t - 47.2),
+    ) * settings.turbulence
+}
+
+// Same exponential squared fog as fog.fs, 1.0 is no fog
+pub fn fog_factor(distance: f32, fog_density: f32) -> f32 {
+    let d = distance * fog_density;
+    (-(d * d)).exp().clamp(0.0, 1.0)
+}
+
+// Fog fades transparent particles out instead of tinting them,
+// alpha blended ones would otherwise show up as fog colored quads.
+// Colors are mixed in linear space like in fog.fs, the result is sRGB
+// again for the unlit shader.
+pub fn apply_fog(color: Color, fog_color: Color, factor: f32) -> Color {
+    let to_linear = |c: u8| (c as f32 / 255.0).powf(2.2);
+    let channel = |c: u8, fog: u8| {
+        let linear = lerp(to_linear(fog), to_linear(c), factor);
+        (linear.powf(1.0 / 2.2) * 255.0).round() as u8
+    };
+    Color::new(
+        channel(color.r, fog_color.r),
+        channel(color.g, fog_color.g),
+        channel(color.b, fog_color.b),
+        (color.a as f32 * factor) as u8,
+    )
+}
+
+// Draws vertex colors and textures without lighting into the linear HDR scene
+pub fn load_unlit_shader(rl: &mut RaylibHandle, thread: &RaylibThread) -> Shader {
+    let unlit_shader = shader_source(UNLIT_SHADER, ShaderStage::Fragment, &[]);
+    rl.load_shader_from_memory(thread, None, Some(&unlit_shader))
+}
+
+// Random direction inside a cone around axis, u and v are uniform in [0..1]
+pub fn cone_direction(axis: Vector3, spread: f32, u: f32, v: f32) -> Vector3 {
+    let axis = axis.normalized();
+
+    // Uniform on the spherical cap
+    let cos_theta = 1.0 - u * (1.0 - spread.cos());
+    let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+    let phi = v * 2.0 * PI;
+
+    // Any vector not parallel to axis works for the basis
+    let helper = if axis.y.abs() < 0.99 {
+        Vector3::up()
+    } else {
+        Vector3::right()
+    };
+    let tangent = axis.cross(helper).normalized();
+    let bitangent = axis.cross(tangent);
+
+    axis * cos_theta + (tangent * phi.cos() + bitangent * phi.sin()) * sin_theta
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    let channel = |a: u8, b: u8| lerp(a as f32, b as f32, t).round() as u8;
+    Color::new(
+        channel(a.r, b.r),
+        channel(a.g, b.g),
+        channel(a.b, b.b),
+        channel(a.a, b.a),
+    )
+}
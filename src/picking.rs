@@ -0,0 +1,152 @@
+use raylib::prelude::*;
+
+// Integer scale of the render target on screen, the largest that fits the
+// window. Windows smaller than the target show it unscaled and cropped.
+pub fn render_target_scaling(screen_size: (i32, i32), target_size: (i32, i32)) -> i32 {
+    let scale_x = screen_size.0 / target_size.0;
+    let scale_y = screen_size.1 / target_size.1;
+    scale_x.min(scale_y).max(1)
+}
+
+// Screen position of the top left corner of the centered render target
+pub fn render_target_origin(
+    screen_size: (i32, i32),
+    target_size: (i32, i32),
+    scaling: i32,
+) -> Vector2 {
+    Vector2::new(
+        (screen_size.0 - target_size.0 * scaling) as f32 / 2.0,
+        (screen_size.1 - target_size.1 * scaling) as f32 / 2.0,
+    )
+}
+
+// Window position in render target pixels, None on the letterbox bars
+pub fn screen_to_target(
+    position: Vector2,
+    screen_size: (i32, i32),
+    target_size: (i32, i32),
+) -> Option<Vector2> {
+    let scaling = render_target_scaling(screen_size, target_size);
+    let origin = render_target_origin(screen_size, target_size, scaling);
+    let target = (position - origin) * (1.0 / scaling as f32);
+
+    let inside = target.x >= 0.0
+        && target.y >= 0.0
+        && target.x < target_size.0 as f32
+        && target.y < target_size.1 as f32;
+    inside.then_some(target)
+}
+
+// Same ray as GetMouseRay, but for a point on a view of the given size
+// instead of the window, so it works through the render target
+pub fn camera_ray(camera: &Camera3D, position: Vector2, view_size: Vector2) -> Ray {
+    let forward = (camera.target - camera.position).normalized();
+    let right = forward.cross(camera.up).normalized();
+    let up = right.cross(forward);
+
+    // Normalized device coordinates, y points up
+    let x = 2.0 * position.x / view_size.x - 1.0;
+    let y = 1.0 - 2.0 * position.y / view_size.y;
+    let aspect = view_size.x / view_size.y;
+
+    match camera.camera_type() {
+        CameraProjection::CAMERA_PERSPECTIVE => {
+            let half_height = (camera.fovy.to_radians() * 0.5).tan();
+            let direction = forward + right * (x * half_height * aspect) + up * (y * half_height);
+            Ray {
+                position: camera.position,
+                direction: direction.normalized(),
+            }
+        }
+        // Orthographic fovy is the view height in world units
+        CameraProjection::CAMERA_ORTHOGRAPHIC => {
+            let half_height = camera.fovy * 0.5;
+            let offset = right * (x * half_height * aspect) + up * (y * half_height);
+            Ray {
+                position: camera.position + offset,
+                direction: forward,
+            }
+        }
+    }
+}
+
+// Nearest surface hit by a ray
+#[derive(Clone, Copy, Debug)]
+pub struct PickHit<T> {
+    pub object: T,
+    pub distance: f32,
+    pub point: Vector3,
+    pub normal: Vector3,
+}
+
+// Mesh that can be hit by a ray, bounds are in model space
+pub struct Pickable<T> {
+    pub object: T,
+    pub mesh: ffi::Mesh,
+    pub bounds: BoundingBox,
+    pub transform: Matrix,
+}
+
+impl<T> Pickable<T> {
+    // Only the mesh handle is copied, the mesh must stay loaded while picking
+    pub fn new(object: T, mesh: &impl RaylibMesh, bounds: BoundingBox, transform: Matrix) -> Self {
+        Self {
+            object,
+            mesh: *mesh.as_ref(),
+            bounds,
+            transform,
+        }
+    }
+}
+
+// Nearest hit of the ray. Boxes reject objects the ray misses cheaply,
+// only objects whose box is hit closer than the best hit so far test triangles.
+pub fn pick<T: Copy>(
+    ray: Ray,
+    objects: impl IntoIterator<Item = Pickable<T>>,
+) -> Option<PickHit<T>> {
+    let mut nearest: Option<PickHit<T>> = None;
+
+    for pickable in objects {
+        let bounds = transform_box(&pickable.bounds, &pickable.transform);
+        let box_hit = bounds.get_ray_collision_box(ray);
+        // Rays starting inside the box hit it too
+        if !box_hit.hit || nearest.is_some_and(|nearest| box_hit.distance > nearest.distance) {
+            continue;
+        }
+
+        let hit: RayCollision = unsafe {
+            ffi::GetRayCollisionMesh(ray.into(), pickable.mesh, pickable.transform.into()).into()
+        };
+        if hit.hit && nearest.is_none_or(|nearest| hit.distance < nearest.distance) {
+            nearest = Some(PickHit {
+                object: pickable.object,
+                distance: hit.distance,
+                point: hit.point,
+                normal: hit.normal,
+            });
+        }
+    }
+
+    nearest
+}
+
+// World space box around the transformed corners of a model space box
+pub fn transform_box(bounds: &BoundingBox, transform: &Matrix) -> BoundingBox {
+    let mut min = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
+    let mut max = Vector3::new(f32::MIN, f32::MIN, f32::MIN);
+
+    for corner in 0..8 {
+        let axis = |bit: i32, min: f32, max: f32| if corner & bit == 0 { min } else { max };
+        let point = Vector3::new(
+            axis(1, bounds.min.x, bounds.max.x),
+            axis(2, bounds.min.y, bounds.max.y),
+            axis(4, bounds.min.z, bounds.max.z),
+        )
+        .transform_with(*transform);
+        min = min.min(point);
+        max = max.max(point);
+    }
+
+    BoundingBox::new(min, max)
+}
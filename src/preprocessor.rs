@@ -0,0 +1,324 @@
+use crate::constants::*;
+use crate::fog::*;
+use crate::light::*;
+use crate::retro::*;
+use crate::shader::*;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Feature flag of every shader writing into the scene target
+pub const SCENE_SRGB_DEFINE: &str = "SCENE_SRGB";
+
+static SCENE_SRGB: AtomicBool = AtomicBool::new(false);
+
+// Shaders loaded afterwards store sRGB encoded color in the scene target,
+// set when it fell back to 8 bits per channel
+pub fn set_scene_srgb(scene_srgb: bool) {
+    SCENE_SRGB.store(scene_srgb, Ordering::Relaxed);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShaderStage {
+    Vertex,
+    Fragment,
+}
+
+// Constants used by both Rust and GLSL, defined once on the Rust side
+pub fn shared_defines() -> Vec<(&'static str, String)> {
+    vec![
+        ("MAX_LIGHTS", MAX_LIGHTS.to_string()),
+        (
+            "LIGHT_DIRECTIONAL",
+            (LightType::LightDirectional as i32).to_string(),
+        ),
+        ("LIGHT_POINT", (LightType::LightPoint as i32).to_string()),
+        ("TONEMAP_NONE", (ToneMapper::None as i32).to_string()),
+        (
+            "TONEMAP_REINHARD",
+            (ToneMapper::Reinhard as i32).to_string(),
+        ),
+        ("TONEMAP_ACES", (ToneMapper::Aces as i32).to_string()),
+        ("MAX_PALETTE_SIZE", MAX_PALETTE_SIZE.to_string()),
+        (
+            "FOG_MODE_VOLUMETRIC",
+            (FogMode::Volumetric as i32).to_string(),
+        ),
+        ("FOG_MODE_SIMPLE", (FogMode::Simple as i32).to_string()),
+        ("FOG_MODE", (FOG_MODE as i32).to_string()),
+    ]
+}
+
+// Shader for the GLSL version of the platform, with the shared defines
+// and the feature flags of the variant
+pub fn shader_source(source: &str, stage: ShaderStage, flags: &[&str]) -> String {
+    let mut defines = shared_defines();
+    defines.extend(flags.iter().map(|flag| (*flag, String::new())));
+    if SCENE_SRGB.load(Ordering::Relaxed) {
+        defines.push((SCENE_SRGB_DEFINE, String::new()));
+    }
+
+    preprocess(source, stage, GLSL_VERSION, &defines, &SHADER_CHUNKS).unwrap()
+}
+
+// Turns a shader written in GLSL 330 without a #version line into a shader for
+// the GLSL version. Defines come right after the version, so the shader and every
+// chunk it includes can use them, a define with an empty value is a feature flag.
+pub fn preprocess(
+    source: &str,
+    stage: ShaderStage,
+    version: i32,
+    defines: &[(&str, String)],
+    chunks: &[(&str, &str)],
+) -> Result<String, String> {
+    if version != 100 && version != 330 {
+        return Err(format!("unsupported GLSL version {}", version));
+    }
+
+    let mut body = String::with_capacity(source.len());
+    resolve_includes(source, chunks, &mut Vec::new(), &mut body)?;
+
+    let mut shader = String::with_capacity(body.len() + defines.len() * 32);
+    writeln!(shader, "#version {}", version).unwrap();
+    if version == 100 && stage == ShaderStage::Fragment {
+        shader.push_str("precision mediump float;\n");
+    }
+    for (name, value) in defines {
+        if value.is_empty() {
+            writeln!(shader, "#define {}", name).unwrap();
+        } else {
+            writeln!(shader, "#define {} {}", name, value).unwrap();
+        }
+    }
+    shader.push('\n');
+
+    if version == 100 {
+        shader.push_str(&to_glsl100(&body, stage));
+    } else {
+        shader.push_str(&body);
+    }
+    Ok(shader)
+}
+
+// Replaces #include "name" lines with the chunk of that name. Each chunk is
+// included once, so chunks can include what they use without duplicates.
+fn resolve_includes<'a>(
+    source: &str,
+    chunks: &[(&'a str, &'a str)],
+    included: &mut Vec<&'a str>,
+    output: &mut String,
+) -> Result<(), String> {
+    for line in source.lines() {
+        let Some(include) = line.trim_start().strip_prefix("#include") else {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        };
+
+        let name = include
+            .trim()
+            .strip_prefix('"')
+            .and_then(|name| name.strip_suffix('"'))
+            .ok_or_else(|| format!("malformed shader include {}", line.trim()))?;
+        let (name, chunk) = chunks
+            .iter()
+            .find(|(chunk_name, _)| *chunk_name == name)
+            .ok_or_else(|| format!("unknown shader chunk {}", name))?;
+
+        if !included.contains(name) {
+            included.push(name);
+            resolve_includes(chunk, chunks, included, output)?;
+        }
+    }
+
+    Ok(())
+}
+
+// Rewrites GLSL 330 into GLSL 100: inputs become attributes or varyings,
+// the fragment output becomes gl_FragColor and texture lookups get typed names
+pub fn to_glsl100(source: &str, stage: ShaderStage) -> String {
+    let cube_samplers = sampler_cube_names(source);
+    let mut outputs: Vec<String> = Vec::new();
+    let mut output = String::with_capacity(source.len());
+
+    for line in source.lines() {
+        let code = line.trim_start();
+        let indent = &line[..line.len() - code.len()];
+
+        let line = if let Some(declaration) = code.strip_prefix("in ") {
+            let qualifier = match stage {
+                ShaderStage::Vertex => "attribute",
+                ShaderStage::Fragment => "varying",
+            };
+            format!("{}{} {}", indent, qualifier, declaration)
+        } else if let Some(declaration) = code.strip_prefix("out ") {
+            if stage == ShaderStage::Fragment {
+                // GLSL 100 has the single built in output only
+                let name = declaration.trim_end_matches(';').split_whitespace().last();
+                outputs.extend(name.map(str::to_string));
+                continue;
+            }
+            format!("{}varying {}", indent, declaration)
+        } else {
+            line.to_string()
+        };
+
+        // Comments are kept as they are
+        let (code, comment) = line.split_at(line.find("//").unwrap_or(line.len()));
+        output.push_str(&rename_identifiers(code, &outputs, &cube_samplers));
+        output.push_str(comment);
+        output.push('\n');
+    }
+
+    output
+}
+
+fn rename_identifiers(code: &str, outputs: &[String], cube_samplers: &[String]) -> String {
+    let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut output = String::with_capacity(code.len());
+    let mut rest = code;
+
+    while let Some(start) = rest.find(is_identifier) {
+        output.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find(|c: char| !is_identifier(c))
+            .map_or(rest.len(), |end| start + end);
+        let identifier = &rest[start..end];
+        rest = &rest[end..];
+
+        // Sampler type decides the lookup function, the sampler is the first argument
+        let arguments = rest.trim_start().strip_prefix('(');
+        let renamed = match (identifier, arguments) {
+            ("texture", Some(arguments)) => {
+                let sampler = arguments.trim_start();
+                let sampler = &sampler[..sampler
+                    .find(|c: char| !is_identifier(c))
+                    .unwrap_or(sampler.len())];
+                if cube_samplers.iter().any(|name| name == sampler) {
+                    "textureCube"
+                } else {
+                    "texture2D"
+                }
+            }
+            _ if outputs.iter().any(|name| name == identifier) => "gl_FragColor",
+            _ => identifier,
+        };
+        output.push_str(renamed);
+    }
+
+    output.push_str(rest);
+    output
+}
+
+// Names of the samplerCube uniforms declared in the source
+fn sampler_cube_names(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter_map(|line| {
+            let mut words = line.split_whitespace();
+            words.find(|word| *word == "samplerCube")?;
+            words
+                .next()
+                .map(|name| name.trim_end_matches(';').to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHUNKS: [(&str, &str); 2] = [
+        ("a.glsl", "float a;\n"),
+        ("b.glsl", "#include \"a.glsl\"\nfloat b;\n"),
+    ];
+
+    fn includes(source: &str) -> Result<String, String> {
+        let mut output = String::new();
+        resolve_includes(source, &CHUNKS, &mut Vec::new(), &mut output)?;
+        Ok(output)
+    }
+
+    #[test]
+    fn preprocess_puts_version_precision_and_defines_first() {
+        let defines = [("MAX_LIGHTS", "4".to_string()), ("TOON", String::new())];
+        let shader = preprocess(
+            "void main() {}\n",
+            ShaderStage::Fragment,
+            100,
+            &defines,
+            &[],
+        );
+
+        assert_eq!(
+            shader.unwrap(),
+            "#version 100\nprecision mediump float;\n#define MAX_LIGHTS 4\n#define TOON\n\nvoid main() {}\n"
+        );
+    }
+
+    #[test]
+    fn preprocess_adds_precision_to_glsl100_fragment_shaders_only() {
+        let defines = [("TOON", String::new())];
+        for (stage, version) in [
+            (ShaderStage::Vertex, 100),
+            (ShaderStage::Vertex, 330),
+            (ShaderStage::Fragment, 330),
+        ] {
+            let shader = preprocess("", stage, version, &defines, &[]).unwrap();
+            assert_eq!(shader, format!("#version {}\n#define TOON\n\n", version));
+        }
+    }
+
+    #[test]
+    fn preprocess_rejects_unsupported_versions() {
+        assert!(preprocess("", ShaderStage::Vertex, 120, &[], &[]).is_err());
+    }
+
+    #[test]
+    fn chunks_included_twice_are_emitted_once() {
+        let output = includes("#include \"b.glsl\"\n#include \"a.glsl\"\nvoid main() {}\n");
+        assert_eq!(output.unwrap(), "float a;\nfloat b;\nvoid main() {}\n");
+    }
+
+    #[test]
+    fn unknown_and_malformed_includes_are_errors() {
+        assert!(includes("#include \"c.glsl\"\n").is_err());
+        assert!(includes("#include a.glsl\n").is_err());
+        assert!(includes("#include \"a.glsl\n").is_err());
+    }
+
+    #[test]
+    fn glsl100_inputs_and_outputs_per_stage() {
+        let vertex = "in vec3 vertexPosition;\nout vec2 fragTexCoord;\n";
+        assert_eq!(
+            to_glsl100(vertex, ShaderStage::Vertex),
+            "attribute vec3 vertexPosition;\nvarying vec2 fragTexCoord;\n"
+        );
+
+        let fragment = "in vec2 fragTexCoord;\nout vec4 finalColor;\nvoid main() { finalColor = vec4(1.0); }\n";
+        assert_eq!(
+            to_glsl100(fragment, ShaderStage::Fragment),
+            "varying vec2 fragTexCoord;\nvoid main() { gl_FragColor = vec4(1.0); }\n"
+        );
+    }
+
+    #[test]
+    fn glsl100_texture_lookups_follow_the_sampler_type() {
+        let source = "uniform sampler2D texture0;\nuniform samplerCube environmentMap;\n\
+            vec4 a = texture(texture0, uv);\nvec4 b = texture( environmentMap, direction);\n";
+        assert_eq!(
+            to_glsl100(source, ShaderStage::Fragment),
+            "uniform sampler2D texture0;\nuniform samplerCube environmentMap;\n\
+            vec4 a = texture2D(texture0, uv);\nvec4 b = textureCube( environmentMap, direction);\n"
+        );
+    }
+
+    #[test]
+    fn glsl100_leaves_comments_alone() {
+        let source =
+            "out vec4 finalColor;\nfinalColor = texture(t, uv); // texture(t, uv) to finalColor\n";
+        assert_eq!(
+            to_glsl100(source, ShaderStage::Fragment),
+            "gl_FragColor = texture2D(t, uv); // texture(t, uv) to finalColor\n"
+        );
+    }
+}
@@ -0,0 +1,10 @@
+use raylib::prelude::*;
+
+// Copy of the slice in memory allocated with raylib MemAlloc, for data
+// raylib takes ownership of and frees itself
+pub unsafe fn raylib_copy<T: Copy>(data: &[T]) -> *mut T {
+    let size = std::mem::size_of_val(data);
+    let copy = ffi::MemAlloc(size as u32) as *mut T;
+    std::ptr::copy_nonoverlapping(data.as_ptr(), copy, data.len());
+    copy
+}
@@ -0,0 +1,78 @@
+use crate::constants::*;
+use crate::shader::*;
+use raylib::prelude::*;
+
+// Shader define enabling the retro variant of the lighting shader
+pub const RETRO_DEFINE: &str = "RETRO";
+
+pub const MAX_PALETTE_SIZE: u32 = 32;
+
+pub struct RetroSettings {
+    // Grid vertices are snapped to, normally the render target size
+    pub resolution: Vector2,
+    // 0.0 disables dithering, 1.0 spreads it across a full color step
+    pub dither_strength: f32,
+}
+
+impl Default for RetroSettings {
+    fn default() -> Self {
+        Self {
+            resolution: Vector2::new(SCREEN_WIDTH, SCREEN_HEIGHT),
+            dither_strength: 0.25,
+        }
+    }
+}
+
+pub struct RetroPalette {
+    pub texture: Texture2D,
+    pub size: i32,
+}
+
+impl RetroPalette {
+    // Extracts up to MAX_PALETTE_SIZE unique colors from the image
+    // and stores them in a (size x 1) texture
+    pub fn from_image(rl: &mut RaylibHandle, thread: &RaylibThread, img: &Image) -> Self {
+        let colors = img.extract_palette(MAX_PALETTE_SIZE);
+        let size = colors.len().max(1) as i32;
+
+        let mut palette_img = Image::gen_image_color(size, 1, Color::BLACK);
+        for (i, color) in colors.iter().enumerate() {
+            palette_img.draw_pixel(i as i32, 0, *color);
+        }
+
+        let texture = rl.load_texture_from_image(thread, &palette_img).unwrap();
+        texture.set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_POINT);
+
+        Self { texture, size }
+    }
+}
+
+// Sets up retro uniforms of a shader loaded with RETRO_DEFINE. The palette
+// is applied by the tone mapping pass, see ToneMapping::set_retro_palette.
+pub fn setup_retro_shader(shader: &mut Shader, settings: &RetroSettings) {
+    let resolution_loc = shader.get_shader_location("resolution");
+    shader.set_shader_value(resolution_loc, settings.resolution);
+}
+
+// Switches a material to the retro shader variant
+pub fn apply_retro_material(material: &mut WeakMaterial, shader: &Shader) {
+    material.shader = *shader.as_ref();
+}
+
+// Retro materials mark their pixels with zero alpha, the tone mapping pass
+// snaps them to the palette. Blending would hide them, so it is turned off.
+pub fn draw_retro_mesh(
+    d: &mut impl RaylibDraw3D,
+    mesh: &impl RaylibMesh,
+    material: &WeakMaterial,
+    transform: Matrix,
+) {
+    unsafe {
+        ffi::rlDrawRenderBatchActive();
+        ffi::rlDisableColorBlend();
+    }
+    draw_lit_mesh(d, mesh, material, transform);
+    unsafe {
+        ffi::rlEnableColorBlend();
+    }
+}
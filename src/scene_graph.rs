@@ -0,0 +1,163 @@
+use crate::light::*;
+use crate::shader::*;
+use raylib::prelude::*;
+
+pub type NodeId = usize;
+
+#[derive(Clone, Copy, Debug)]
+pub struct LocalTransform {
+    pub translation: Vector3,
+    pub rotation: Quaternion,
+    pub scale: Vector3,
+}
+
+impl Default for LocalTransform {
+    fn default() -> Self {
+        Self {
+            translation: Vector3::zero(),
+            rotation: Quaternion::identity(),
+            scale: Vector3::one(),
+        }
+    }
+}
+
+impl LocalTransform {
+    pub fn from_translation(translation: Vector3) -> Self {
+        Self {
+            translation,
+            ..Default::default()
+        }
+    }
+
+    // Scales, then rotates, then translates
+    pub fn matrix(&self) -> Matrix {
+        Matrix::scale(self.scale.x, self.scale.y, self.scale.z)
+            * self.rotation.to_matrix()
+            * Matrix::translate(self.translation.x, self.translation.y, self.translation.z)
+    }
+}
+
+pub struct Node {
+    // Relative to the parent, or to the world for root nodes
+    pub transform: LocalTransform,
+    // Axis times radians per second, in parent space
+    pub angular_velocity: Vector3,
+    // Light following the node, its position and target are in node space
+    pub light: Option<Light>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world: Matrix,
+}
+
+// LocalTransform hierarchy. World matrices are cached and recomputed in update,
+// so changes to local transforms show up after the next update.
+#[derive(Default)]
+pub struct SceneGraph {
+    nodes: Vec<Node>,
+}
+
+impl SceneGraph {
+    pub fn add(&mut self, transform: LocalTransform, parent: Option<NodeId>) -> NodeId {
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            transform,
+            angular_velocity: Vector3::zero(),
+            light: None,
+            parent: None,
+            children: Vec::new(),
+            world: transform.matrix(),
+        });
+        self.set_parent(id, parent);
+        id
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id]
+    }
+
+    // Moves the node under a new parent, None detaches it to the root.
+    // The local transform is kept, so the node moves with its new parent.
+    // Returns false if the parent is the node itself or one of its children.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        if let Some(parent) = parent {
+            if self.is_ancestor(id, parent) {
+                return false;
+            }
+        }
+
+        if let Some(old) = self.nodes[id].parent {
+            self.nodes[old].children.retain(|child| *child != id);
+        }
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(id);
+        }
+        self.nodes[id].parent = parent;
+        true
+    }
+
+    // True if ancestor is the node or any of its parents
+    fn is_ancestor(&self, ancestor: NodeId, id: NodeId) -> bool {
+        let mut current = Some(id);
+        while let Some(node) = current {
+            if node == ancestor {
+                return true;
+            }
+            current = self.nodes[node].parent;
+        }
+        false
+    }
+
+    // Spins nodes by their angular velocity and recomputes world matrices
+    pub fn update(&mut self, delta_time: f32) {
+        for node in &mut self.nodes {
+            node.transform.rotation =
+                integrate_rotation(node.transform.rotation, node.angular_velocity, delta_time);
+        }
+
+        // Parents before children, starting from the roots
+        let mut stack: Vec<NodeId> = (0..self.nodes.len())
+            .filter(|id| self.nodes[*id].parent.is_none())
+            .collect();
+        while let Some(id) = stack.pop() {
+            let local = self.nodes[id].transform.matrix();
+            self.nodes[id].world = match self.nodes[id].parent {
+                Some(parent) => local * self.nodes[parent].world,
+                None => local,
+            };
+            stack.extend_from_slice(&self.nodes[id].children);
+        }
+    }
+
+    // Cached model matrix of the node
+    pub fn world(&self, id: NodeId) -> Matrix {
+        self.nodes[id].world
+    }
+
+    // Uploads lights attached to nodes at their world positions
+    pub fn apply_lights(&self, shader: &mut LightingShader) {
+        for node in &self.nodes {
+            if let Some(light) = &node.light {
+                let mut light = light.clone();
+                light.position = light.position.transform_with(node.world);
+                light.target = light.target.transform_with(node.world);
+                shader.set_light(&light);
+            }
+        }
+    }
+}
+
+// Rotation after turning for delta_time at the angular velocity. Normalized,
+// so rounding errors don't build up into scaling over many frames.
+pub fn integrate_rotation(
+    rotation: Quaternion,
+    angular_velocity: Vector3,
+    delta_time: f32,
+) -> Quaternion {
+    let speed = angular_velocity.length();
+    if speed <= f32::EPSILON {
+        return rotation;
+    }
+
+    let step = Quaternion::from_axis_angle(angular_velocity / speed, speed * delta_time);
+    (step * rotation).normalized()
+}
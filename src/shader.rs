@@ -0,0 +1,189 @@
+use crate::constants::*;
+use crate::fog::*;
+use crate::instancing::*;
+use crate::light::*;
+use crate::preprocessor::*;
+use crate::sky::*;
+use std::ffi::CString;
+use raylib::prelude::*;
+
+// raylib reserves 32 shader locations but uses only the first 26,
+// the rest hold locations of custom per material uniforms
+pub const SHADER_LOC_COLOR_EMISSION: usize = 26;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ToneMapper {
+    None = 0,
+    Reinhard = 1,
+    Aces = 2,
+}
+
+impl ToneMapper {
+    pub fn next(self) -> Self {
+        match self {
+            ToneMapper::None => ToneMapper::Reinhard,
+            ToneMapper::Reinhard => ToneMapper::Aces,
+            ToneMapper::Aces => ToneMapper::None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMapper::None => "none",
+            ToneMapper::Reinhard => "Reinhard",
+            ToneMapper::Aces => "ACES",
+        }
+    }
+}
+
+// Lighting + fog shader together with uniform locations updated every frame
+pub struct LightingShader {
+    pub shader: Shader,
+    pub ambient_loc: i32,
+    pub fog_density_loc: i32,
+    pub fog_color_loc: i32,
+    pub fog_scroll_loc: i32,
+    pub fog_noise_params_loc: i32,
+    // Looked up once, lights are uploaded every frame
+    pub light_locs: [LightLocations; MAX_LIGHTS as usize],
+}
+
+impl LightingShader {
+    pub fn load(rl: &mut RaylibHandle, thread: &RaylibThread, defines: &[&str]) -> Self {
+        let vertex_shader = shader_source(VERTEX_SHADER, ShaderStage::Vertex, defines);
+        let fractal_shader = shader_source(FRACTAL_SHADER, ShaderStage::Fragment, defines);
+
+        let mut shader =
+            rl.load_shader_from_memory(thread, Some(&vertex_shader), Some(&fractal_shader));
+        shader.locs_mut()[raylib::consts::ShaderLocationIndex::SHADER_LOC_MATRIX_MODEL as usize] =
+            if defines.contains(&INSTANCING_DEFINE) {
+                // raylib sends instance transforms to the attribute at the model matrix location
+                let name = CString::new("instanceTransform").unwrap();
+                unsafe { ffi::GetShaderLocationAttrib(*shader.as_ref(), name.as_ptr()) }
+            } else {
+                shader.get_shader_location("matModel")
+            };
+        shader.locs_mut()[raylib::consts::ShaderLocationIndex::SHADER_LOC_VECTOR_VIEW as usize] =
+            shader.get_shader_location("viewPos");
+        shader.locs_mut()[SHADER_LOC_COLOR_EMISSION] = shader.get_shader_location("colEmission");
+        shader.locs_mut()[FOG_NOISE_LOC as usize] = shader.get_shader_location("fogNoiseMap");
+
+        let mut lighting_shader = Self {
+            ambient_loc: shader.get_shader_location("ambient"),
+            fog_density_loc: shader.get_shader_location("fogDensity"),
+            fog_color_loc: shader.get_shader_location("fogColor"),
+            fog_scroll_loc: shader.get_shader_location("fogScroll"),
+            fog_noise_params_loc: shader.get_shader_location("fogNoiseParams"),
+            light_locs: std::array::from_fn(|index| LightLocations::new(&shader, index as i32)),
+            shader,
+        };
+        // Uniform fog until animated fog is applied
+        lighting_shader.set_fog_noise(Vector2::zero(), 1.0, 0.0, 0.0, 0.0);
+        lighting_shader
+    }
+
+    pub fn set_ambient(&mut self, ambient: Vector4) {
+        self.shader.set_shader_value(self.ambient_loc, ambient);
+    }
+
+    // Uploads the light to its slot in this shader
+    pub fn set_light(&mut self, light: &Light) {
+        let Some(locations) = self.light_locs.get(light.index as usize) else {
+            return;
+        };
+        let mut light = light.clone();
+        locations.bind(&mut light);
+        update_light_values(&mut self.shader, light);
+    }
+
+    pub fn set_fog_density(&mut self, fog_density: f32) {
+        self.shader
+            .set_shader_value(self.fog_density_loc, fog_density);
+    }
+
+    pub fn set_fog_color(&mut self, fog_color: Color) {
+        self.shader
+            .set_shader_value(self.fog_color_loc, color_to_vec3(fog_color));
+    }
+
+    // Fog density is scaled by noise in [1 - strength..1 + strength] and by
+    // exp(-height_falloff*height) above base_height, see AnimatedFog
+    pub fn set_fog_noise(
+        &mut self,
+        scroll: Vector2,
+        scale: f32,
+        strength: f32,
+        height_falloff: f32,
+        base_height: f32,
+    ) {
+        self.shader.set_shader_value(self.fog_scroll_loc, scroll);
+        self.shader.set_shader_value(
+            self.fog_noise_params_loc,
+            Vector4::new(scale, strength, height_falloff, base_height),
+        );
+    }
+
+    // Update the light shader with the camera view position
+    pub fn set_view_position(&mut self, position: Vector3) {
+        let loc = self.shader.locs()
+            [raylib::consts::ShaderLocationIndex::SHADER_LOC_VECTOR_VIEW as usize];
+        self.shader.set_shader_value(loc, position);
+    }
+}
+
+// Emission is stored in the material emission map:
+// color is the tint and value is the strength, which may go above 1.0
+pub fn set_material_emission(material: &mut WeakMaterial, color: Color, strength: f32) {
+    let map = &mut material.maps_mut()[MaterialMapIndex::MATERIAL_MAP_EMISSION as usize];
+    map.color = color.into();
+    map.value = strength;
+}
+
+// raylib uploads only the diffuse color of a material,
+// so emission has to be sent right before the mesh is drawn
+pub fn upload_material_emission(material: &WeakMaterial) {
+    let mut shader = unsafe { WeakShader::from_raw(material.shader) };
+    let loc = shader.locs()[SHADER_LOC_COLOR_EMISSION];
+    if loc < 0 {
+        return;
+    }
+
+    let map = &material.maps()[MaterialMapIndex::MATERIAL_MAP_EMISSION as usize];
+    let color: Vector4 = map.color().color_normalize();
+    shader.set_shader_value(
+        loc,
+        Vector4::new(color.x, color.y, color.z, color.w * map.value()),
+    );
+}
+
+// Draws a model using the lighting shader features raylib doesn't upload itself,
+// transform is applied after the model transform like in DrawModel
+pub fn draw_lit_model(d: &mut impl RaylibDraw3D, model: &Model, transform: Matrix) {
+    let transform = *model.transform() * transform;
+
+    let mesh_material =
+        unsafe { std::slice::from_raw_parts(model.meshMaterial, model.meshCount as usize) };
+
+    for (mesh, material_index) in model.meshes().iter().zip(mesh_material) {
+        draw_lit_mesh(
+            d,
+            mesh,
+            &model.materials()[*material_index as usize],
+            transform,
+        );
+    }
+}
+
+pub fn draw_lit_mesh(
+    _d: &mut impl RaylibDraw3D,
+    mesh: &impl RaylibMesh,
+    material: &WeakMaterial,
+    transform: Matrix,
+) {
+    upload_material_emission(material);
+
+    // NOTE: raylib-rs draw_mesh takes the mesh by value and unloads it
+    unsafe {
+        ffi::DrawMesh(*mesh.as_ref(), *material.as_ref(), transform.into());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::noise::*;
+use raylib::prelude::*;
+
+pub struct ShakeSettings {
+    // Offset at full trauma in render target pixels
+    pub max_offset: f32,
+    // Rotation at full trauma in degrees
+    pub max_rotation: f32,
+    // Trauma lost per second
+    pub decay: f32,
+    // How fast the noise changes, in samples per second
+    pub frequency: f32,
+}
+
+impl Default for ShakeSettings {
+    fn default() -> Self {
+        Self {
+            max_offset: 6.0,
+            max_rotation: 3.0,
+            decay: 1.5,
+            frequency: 20.0,
+        }
+    }
+}
+
+// Trauma based camera shake, shake strength is trauma squared,
+// so small hits barely move the camera while big ones add up quickly
+pub struct CameraShake {
+    pub settings: ShakeSettings,
+    pub trauma: f32,
+    time: f32,
+    noise: Noise,
+}
+
+// Two octaves add a bit of jitter on top of the main sway
+const SHAKE_NOISE: Fractal = Fractal {
+    kind: NoiseKind::Perlin,
+    octaves: 2,
+    frequency: 1.0,
+    lacunarity: 2.0,
+    gain: 0.5,
+};
+
+impl CameraShake {
+    pub fn new(settings: ShakeSettings) -> Self {
+        Self {
+            settings,
+            trauma: 0.0,
+            time: 0.0,
+            noise: Noise::new(0x5EED),
+        }
+    }
+
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+        self.trauma = (self.trauma - self.settings.decay * delta_time).max(0.0);
+    }
+
+    fn shake(&self) -> f32 {
+        self.trauma * self.trauma
+    }
+
+    // Offset in whole render target pixels, so the image stays crisp
+    pub fn offset(&self) -> Vector2 {
+        let t = self.time * self.settings.frequency;
+        let amount = self.settings.max_offset * self.shake();
+
+        Vector2::new(
+            (amount * self.signed_noise(0, t)).round(),
+            (amount * self.signed_noise(1, t)).round(),
+        )
+    }
+
+    // Rotation in degrees
+    pub fn rotation(&self) -> f32 {
+        let t = self.time * self.settings.frequency;
+        self.settings.max_rotation * self.shake() * self.signed_noise(2, t)
+    }
+
+    // Shaken copy of the camera, the original is left untouched
+    pub fn apply_2d(&self, camera: &Camera2D) -> Camera2D {
+        let mut shaken = *camera;
+        shaken.offset += self.offset();
+        shaken.rotation += self.rotation();
+        shaken
+    }
+
+    // Pixel offset is converted to world units at the target distance,
+    // render_height is the height of the render target in pixels
+    pub fn apply_3d(&self, camera: &Camera3D, render_height: f32) -> Camera3D {
+        let offset = self.offset();
+        let view = camera.target - camera.position;
+        let forward = view.normalized();
+        let right = forward.cross(camera.up).normalized();
+        let up = right.cross(forward);
+
+        let world_per_pixel =
+            2.0 * view.length() * (camera.fovy.to_radians() * 0.5).tan() / render_height;
+        // Camera moves against the offset, so the image moves the same way as in 2D
+        let shift = (up * offset.y - right * offset.x) * world_per_pixel;
+
+        // Roll around the view direction
+        let roll = Matrix::rotate(forward, self.rotation().to_radians());
+
+        let mut shaken = *camera;
+        shaken.position += shift;
+        shaken.target += shift;
+        shaken.up = up.transform_with(roll);
+        shaken
+    }
+
+    // Smooth noise in [-1..1], channels are rows far apart in the noise
+    // so they give independent curves
+    fn signed_noise(&self, channel: u32, t: f32) -> f32 {
+        self.noise
+            .fbm2(&SHAKE_NOISE, t, channel as f32 * 31.7 + 0.5)
+    }
+}
@@ -0,0 +1,322 @@
+use raylib::prelude::*;
+
+// raylib samples glTF animations into frames this many seconds apart
+pub const ANIMATION_FRAME_TIME: f32 = 0.017;
+
+#[derive(Clone, Copy, Debug)]
+struct Playback {
+    clip: usize,
+    // Seconds since the clip started
+    time: f32,
+    // Clips that don't loop hold their last frame
+    looping: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Fade {
+    // Weight of the outgoing clip when the crossfade started
+    from: f32,
+    elapsed: f32,
+    duration: f32,
+}
+
+// Plays the skeletal animation clips of a model. A second clip can be mixed
+// in, either held at a weight or fading out during a crossfade.
+pub struct Animator {
+    pub clips: Vec<ModelAnimation>,
+    pub speed: f32,
+    current: Playback,
+    blend: Option<Playback>,
+    // Weight of the blend clip, the current clip gets the rest
+    blend_weight: f32,
+    fade: Option<Fade>,
+    pose: Vec<Transform>,
+    blend_pose: Vec<Transform>,
+}
+
+impl Animator {
+    // Models without clips can use an animator too, it leaves them in bind pose
+    pub fn new(clips: Vec<ModelAnimation>) -> Self {
+        Self {
+            clips,
+            speed: 1.0,
+            current: Playback {
+                clip: 0,
+                time: 0.0,
+                looping: true,
+            },
+            blend: None,
+            blend_weight: 0.0,
+            fade: None,
+            pose: Vec::new(),
+            blend_pose: Vec::new(),
+        }
+    }
+
+    pub fn clip_index(&self, name: &str) -> Option<usize> {
+        self.clips.iter().position(|clip| clip_name(clip) == name)
+    }
+
+    pub fn current_clip(&self) -> usize {
+        self.current.clip
+    }
+
+    // Switches to the clip at once, dropping any blend
+    pub fn play(&mut self, clip: usize, looping: bool) {
+        self.current = Playback {
+            clip,
+            time: 0.0,
+            looping,
+        };
+        self.blend = None;
+        self.blend_weight = 0.0;
+        self.fade = None;
+    }
+
+    // Starts the clip and fades out the playing one over duration seconds.
+    // During a running crossfade the clip with more weight is faded out,
+    // the other one is dropped.
+    pub fn crossfade(&mut self, clip: usize, looping: bool, duration: f32) {
+        if duration <= 0.0 {
+            self.play(clip, looping);
+            return;
+        }
+
+        let (outgoing, weight) = match self.blend {
+            Some(blend) if self.blend_weight > 0.5 => (blend, self.blend_weight),
+            _ => (self.current, 1.0 - self.blend_weight),
+        };
+
+        self.current = Playback {
+            clip,
+            time: 0.0,
+            looping,
+        };
+        self.blend = Some(outgoing);
+        self.blend_weight = weight;
+        self.fade = Some(Fade {
+            from: weight,
+            elapsed: 0.0,
+            duration,
+        });
+    }
+
+    // Mixes a looping clip into the current one at a fixed weight, 0.0 removes it
+    pub fn set_blend(&mut self, clip: usize, weight: f32) {
+        self.fade = None;
+        if weight <= 0.0 {
+            self.blend = None;
+            self.blend_weight = 0.0;
+            return;
+        }
+
+        // Changing only the weight keeps the clip playing smoothly
+        let time = match self.blend {
+            Some(blend) if blend.clip == clip => blend.time,
+            _ => 0.0,
+        };
+        self.blend = Some(Playback {
+            clip,
+            time,
+            looping: true,
+        });
+        self.blend_weight = weight.min(1.0);
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        let step = delta_time * self.speed;
+        self.current.time += step;
+        if let Some(blend) = &mut self.blend {
+            blend.time += step;
+        }
+
+        if let Some(fade) = &mut self.fade {
+            fade.elapsed += delta_time;
+            if fade.elapsed >= fade.duration {
+                self.blend = None;
+                self.blend_weight = 0.0;
+                self.fade = None;
+            } else {
+                self.blend_weight = fade.from * (1.0 - fade.elapsed / fade.duration);
+            }
+        }
+    }
+
+    // Skins the model meshes with the blended pose. Clips made for another
+    // skeleton are ignored, like raylib does.
+    pub fn apply(&mut self, model: &mut Model) {
+        let Some(clip) = playable_clip(&self.clips, self.current.clip, model) else {
+            return;
+        };
+        sample_pose(
+            clip,
+            self.current.time,
+            self.current.looping,
+            &mut self.pose,
+        );
+
+        if let Some(blend) = self.blend {
+            if let Some(clip) = playable_clip(&self.clips, blend.clip, model) {
+                sample_pose(clip, blend.time, blend.looping, &mut self.blend_pose);
+                for (bone, blend_bone) in self.pose.iter_mut().zip(&self.blend_pose) {
+                    *bone = blend_transform(bone, blend_bone, self.blend_weight);
+                }
+            }
+        }
+
+        skin_model(model, &self.pose);
+    }
+}
+
+fn playable_clip<'a>(
+    clips: &'a [ModelAnimation],
+    clip: usize,
+    model: &Model,
+) -> Option<&'a ModelAnimation> {
+    clips
+        .get(clip)
+        .filter(|clip| clip.boneCount == model.boneCount && clip.frameCount > 0)
+}
+
+pub fn clip_name(clip: &ModelAnimation) -> String {
+    let name: Vec<u8> = clip
+        .name
+        .iter()
+        .take_while(|c| **c != 0)
+        .map(|c| *c as u8)
+        .collect();
+    String::from_utf8_lossy(&name).into_owned()
+}
+
+// Frames around the time and how far it is from the first one to the second.
+// The last frame of a loop matches the first, so loops wrap one frame early.
+pub fn frame_position(frame_count: usize, time: f32, looping: bool) -> (usize, usize, f32) {
+    let last = frame_count.saturating_sub(1);
+    if last == 0 {
+        return (0, 0, 0.0);
+    }
+
+    let position = time.max(0.0) / ANIMATION_FRAME_TIME;
+    let position = if looping {
+        position % last as f32
+    } else {
+        position.min(last as f32)
+    };
+
+    let frame = (position as usize).min(last);
+    (frame, (frame + 1).min(last), position - frame as f32)
+}
+
+// Bone transforms of the clip at the time, interpolated between frames
+pub fn sample_pose(clip: &ModelAnimation, time: f32, looping: bool, pose: &mut Vec<Transform>) {
+    let (from, to, t) = frame_position(clip.frameCount as usize, time, looping);
+    let frame = |index: usize| unsafe {
+        std::slice::from_raw_parts(
+            *clip.framePoses.add(index) as *const Transform,
+            clip.boneCount as usize,
+        )
+    };
+
+    pose.clear();
+    pose.extend(
+        frame(from)
+            .iter()
+            .zip(frame(to))
+            .map(|(a, b)| blend_transform(a, b, t)),
+    );
+}
+
+// Blends from a to b by weight. Rotations take the shortest way around,
+// a quaternion and its negation are the same rotation.
+pub fn blend_transform(a: &Transform, b: &Transform, weight: f32) -> Transform {
+    let dot = a.rotation.x * b.rotation.x
+        + a.rotation.y * b.rotation.y
+        + a.rotation.z * b.rotation.z
+        + a.rotation.w * b.rotation.w;
+    let rotation = if dot < 0.0 {
+        Quaternion::new(-b.rotation.x, -b.rotation.y, -b.rotation.z, -b.rotation.w)
+    } else {
+        b.rotation
+    };
+
+    Transform {
+        translation: a.translation.lerp(b.translation, weight),
+        rotation: a.rotation.slerp(rotation, weight).normalized(),
+        scale: a.scale.lerp(b.scale, weight),
+    }
+}
+
+// Same CPU skinning as UpdateModelAnimation, but with a pose instead of a
+// single animation frame. Both the pose and the bind pose are in model space.
+pub fn skin_model(model: &mut Model, pose: &[Transform]) {
+    if model.bindPose.is_null() || pose.len() != model.boneCount as usize {
+        return;
+    }
+    let bind_pose =
+        unsafe { std::slice::from_raw_parts(model.bindPose as *const Transform, pose.len()) };
+
+    // Rotation from bind pose to pose of each bone
+    let rotations: Vec<Quaternion> = bind_pose
+        .iter()
+        .zip(pose)
+        .map(|(bind, bone)| bone.rotation * bind.rotation.inverted())
+        .collect();
+
+    for mesh in model.meshes_mut() {
+        if mesh.boneIds.is_null() || mesh.boneWeights.is_null() || mesh.animVertices.is_null() {
+            continue;
+        }
+
+        let count = mesh.vertexCount as usize;
+        let (vertices, anim_vertices, bone_ids, bone_weights) = unsafe {
+            (
+                std::slice::from_raw_parts(mesh.vertices as *const Vector3, count),
+                std::slice::from_raw_parts_mut(mesh.animVertices as *mut Vector3, count),
+                std::slice::from_raw_parts(mesh.boneIds, count * 4),
+                std::slice::from_raw_parts(mesh.boneWeights, count * 4),
+            )
+        };
+        let mut normals =
+            (!mesh.normals.is_null() && !mesh.animNormals.is_null()).then(|| unsafe {
+                (
+                    std::slice::from_raw_parts(mesh.normals as *const Vector3, count),
+                    std::slice::from_raw_parts_mut(mesh.animNormals as *mut Vector3, count),
+                )
+            });
+
+        for i in 0..count {
+            let mut vertex = Vector3::zero();
+            let mut normal = Vector3::zero();
+
+            for j in i * 4..i * 4 + 4 {
+                let weight = bone_weights[j];
+                let bone = bone_ids[j] as usize;
+                if weight == 0.0 || bone >= pose.len() {
+                    continue;
+                }
+
+                let skinned = ((vertices[i] - bind_pose[bone].translation) * pose[bone].scale)
+                    .rotate_by(rotations[bone])
+                    + pose[bone].translation;
+                vertex += skinned * weight;
+                if let Some((normals, _)) = &normals {
+                    normal += normals[i].rotate_by(rotations[bone]) * weight;
+                }
+            }
+
+            anim_vertices[i] = vertex;
+            if let Some((_, anim_normals)) = &mut normals {
+                anim_normals[i] = normal;
+            }
+        }
+
+        let size = (count * std::mem::size_of::<Vector3>()) as i32;
+        unsafe {
+            let vbo = std::slice::from_raw_parts(mesh.vboId, 3);
+            ffi::rlUpdateVertexBuffer(vbo[0], mesh.animVertices as *const _, size, 0);
+            if !mesh.animNormals.is_null() {
+                ffi::rlUpdateVertexBuffer(vbo[2], mesh.animNormals as *const _, size, 0);
+            }
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::constants::*;
+use crate::preprocessor::*;
+use raylib::prelude::*;
+
+// Shader define switching the sky shader from gradient to cubemap
+pub const CUBEMAP_DEFINE: &str = "CUBEMAP";
+
+#[derive(Clone, Copy)]
+pub struct SkyGradient {
+    pub top: Color,
+    pub horizon: Color,
+    pub bottom: Color,
+}
+
+impl Default for SkyGradient {
+    fn default() -> Self {
+        Self {
+            top: Color::new(70, 110, 170, 255),
+            horizon: Color::new(170, 180, 190, 255),
+            bottom: Color::new(90, 90, 90, 255),
+        }
+    }
+}
+
+// Background drawn behind the 3D scene, its horizon color is the fog color
+pub struct Sky {
+    pub horizon: Color,
+    shader: Shader,
+    material: WeakMaterial,
+    cube: Mesh,
+    cubemap: Option<Texture2D>,
+    gradient_locs: [i32; 3],
+}
+
+impl Sky {
+    pub fn gradient(rl: &mut RaylibHandle, thread: &RaylibThread, gradient: SkyGradient) -> Self {
+        let shader = load_sky_shader(rl, thread, &[]);
+        let gradient_locs = [
+            shader.get_shader_location("skyTop"),
+            shader.get_shader_location("skyHorizon"),
+            shader.get_shader_location("skyBottom"),
+        ];
+
+        let mut sky = Self::new(rl, thread, shader, None, gradient.horizon);
+        sky.gradient_locs = gradient_locs;
+        sky.set_gradient(&gradient);
+        sky
+    }
+
+    // Horizon color is the average of the middle rows of the four side faces
+    pub fn cubemap(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        img: &Image,
+        layout: CubemapLayout,
+    ) -> Self {
+        let mut shader = load_sky_shader(rl, thread, &[CUBEMAP_DEFINE]);
+        shader.locs_mut()[ShaderLocationIndex::SHADER_LOC_MAP_CUBEMAP as usize] =
+            shader.get_shader_location("environmentMap");
+
+        let cubemap = rl.load_texture_cubemap(thread, img, layout).unwrap();
+        let horizon = horizon_color(img, layout);
+
+        Self::new(rl, thread, shader, Some(cubemap), horizon)
+    }
+
+    fn new(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        shader: Shader,
+        cubemap: Option<Texture2D>,
+        horizon: Color,
+    ) -> Self {
+        let mut material = rl.load_material_default(thread);
+        material.shader = *shader.as_ref();
+        if let Some(cubemap) = &cubemap {
+            material.maps_mut()[MaterialMapIndex::MATERIAL_MAP_CUBEMAP as usize].texture =
+                *cubemap.as_ref();
+        }
+
+        Self {
+            horizon,
+            shader,
+            material,
+            cube: Mesh::gen_mesh_cube(thread, 1.0, 1.0, 1.0),
+            cubemap,
+            gradient_locs: [-1; 3],
+        }
+    }
+
+    // Has no effect on cubemap skies
+    pub fn set_gradient(&mut self, gradient: &SkyGradient) {
+        if self.cubemap.is_some() {
+            return;
+        }
+
+        for (loc, color) in
+            self.gradient_locs
+                .iter()
+                .zip([gradient.top, gradient.horizon, gradient.bottom])
+        {
+            self.shader.set_shader_value(*loc, color_to_vec3(color));
+        }
+        self.horizon = gradient.horizon;
+    }
+
+    // Draw first inside 3D mode, the sky doesn't write depth
+    pub fn draw(&self, _d: &mut impl RaylibDraw3D) {
+        unsafe {
+            ffi::rlDisableBackfaceCulling();
+            ffi::rlDisableDepthMask();
+            ffi::DrawMesh(
+                *self.cube.as_ref(),
+                *self.material.as_ref(),
+                Matrix::identity().into(),
+            );
+            ffi::rlEnableBackfaceCulling();
+            ffi::rlEnableDepthMask();
+        }
+    }
+}
+
+fn load_sky_shader(rl: &mut RaylibHandle, thread: &RaylibThread, defines: &[&str]) -> Shader {
+    let vertex_shader = shader_source(SKY_VERTEX_SHADER, ShaderStage::Vertex, defines);
+    let fractal_shader = shader_source(SKY_SHADER, ShaderStage::Fragment, defines);
+
+    rl.load_shader_from_memory(thread, Some(&vertex_shader), Some(&fractal_shader))
+}
+
+// Top left corners and size of the faces in a cubemap image, in raylib
+// face order +X, -X, +Y, -Y, +Z, -Z. Same layouts as LoadTextureCubemap.
+fn cubemap_faces(width: i32, height: i32, layout: CubemapLayout) -> Option<([(i32, i32); 6], i32)> {
+    let layout = match layout {
+        CubemapLayout::CUBEMAP_LAYOUT_AUTO_DETECT => {
+            if width > height && width / 6 == height {
+                CubemapLayout::CUBEMAP_LAYOUT_LINE_HORIZONTAL
+            } else if width > height && width / 4 == height / 3 {
+                CubemapLayout::CUBEMAP_LAYOUT_CROSS_FOUR_BY_THREE
+            } else if height > width && height / 6 == width {
+                CubemapLayout::CUBEMAP_LAYOUT_LINE_VERTICAL
+            } else if height > width && width / 3 == height / 4 {
+                CubemapLayout::CUBEMAP_LAYOUT_CROSS_THREE_BY_FOUR
+            } else {
+                return None;
+            }
+        }
+        layout => layout,
+    };
+
+    let (size, cells) = match layout {
+        CubemapLayout::CUBEMAP_LAYOUT_LINE_VERTICAL => {
+            (width, [(0, 0), (0, 1), (0, 2), (0, 3), (0, 4), (0, 5)])
+        }
+        CubemapLayout::CUBEMAP_LAYOUT_LINE_HORIZONTAL => {
+            (height, [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0), (5, 0)])
+        }
+        CubemapLayout::CUBEMAP_LAYOUT_CROSS_THREE_BY_FOUR => {
+            (width / 3, [(1, 1), (1, 3), (1, 0), (1, 2), (0, 1), (2, 1)])
+        }
+        CubemapLayout::CUBEMAP_LAYOUT_CROSS_FOUR_BY_THREE => {
+            (width / 4, [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)])
+        }
+        _ => return None,
+    };
+
+    Some((cells.map(|(column, row)| (column * size, row * size)), size))
+}
+
+fn horizon_color(img: &Image, layout: CubemapLayout) -> Color {
+    let colors = img.get_image_data();
+    let width = img.width as usize;
+
+    // The horizon runs through the middle of the side faces, top and bottom faces are skipped.
+    // Unknown layouts fall back to the middle image row.
+    let rows: Vec<(usize, usize)> = match cubemap_faces(img.width, img.height, layout) {
+        Some((faces, size)) => [0, 1, 4, 5]
+            .iter()
+            .map(|face| {
+                let (x, y) = faces[*face];
+                ((y + size / 2) as usize * width + x as usize, size as usize)
+            })
+            .collect(),
+        None => vec![(img.height as usize / 2 * width, width)],
+    };
+
+    let mut sum = [0u32; 3];
+    let mut count = 0;
+    for (start, length) in rows {
+        for color in colors.iter().skip(start).take(length) {
+            sum[0] += color.r as u32;
+            sum[1] += color.g as u32;
+            sum[2] += color.b as u32;
+            count += 1;
+        }
+    }
+
+    let count = count.max(1);
+    Color::new(
+        (sum[0] / count) as u8,
+        (sum[1] / count) as u8,
+        (sum[2] / count) as u8,
+        255,
+    )
+}
+
+pub fn color_to_vec3(color: Color) -> Vector3 {
+    Vector3::new(
+        color.r as f32 / 255.0,
+        color.g as f32 / 255.0,
+        color.b as f32 / 255.0,
+    )
+}
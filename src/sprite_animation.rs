@@ -0,0 +1,255 @@
+use raylib::prelude::*;
+
+// Part of one of the sheet textures
+#[derive(Clone, Copy, Debug)]
+pub struct SpriteFrame {
+    pub texture: usize,
+    pub source: Rectangle,
+}
+
+// Frames can come from several textures, so separate images like
+// tree_left.png and tree_right.png work the same as a single grid sheet
+#[derive(Default)]
+pub struct SpriteSheet {
+    pub textures: Vec<Texture2D>,
+    pub frames: Vec<SpriteFrame>,
+}
+
+impl SpriteSheet {
+    // Adds all cells of a grid texture row by row, returns index of the first frame
+    pub fn add_grid(&mut self, texture: Texture2D, columns: i32, rows: i32) -> usize {
+        let first = self.frames.len();
+        let texture_index = self.textures.len();
+
+        self.frames.extend(
+            grid_frames(texture.width, texture.height, columns, rows)
+                .into_iter()
+                .map(|source| SpriteFrame {
+                    texture: texture_index,
+                    source,
+                }),
+        );
+        self.textures.push(texture);
+
+        first
+    }
+
+    // Whole texture as a single frame
+    pub fn add_texture(&mut self, texture: Texture2D) -> usize {
+        self.add_grid(texture, 1, 1)
+    }
+
+    // Draws the frame centered on position
+    pub fn draw(&self, d: &mut impl RaylibDraw, frame: usize, position: Vector2, tint: Color) {
+        let frame = &self.frames[frame];
+        d.draw_texture_rec(
+            &self.textures[frame.texture],
+            frame.source,
+            position - Vector2::new(frame.source.width / 2f32, frame.source.height / 2f32),
+            tint,
+        );
+    }
+}
+
+pub fn grid_frames(width: i32, height: i32, columns: i32, rows: i32) -> Vec<Rectangle> {
+    let frame_width = (width / columns) as f32;
+    let frame_height = (height / rows) as f32;
+
+    (0..rows)
+        .flat_map(|row| {
+            (0..columns).map(move |column| {
+                Rectangle::new(
+                    column as f32 * frame_width,
+                    row as f32 * frame_height,
+                    frame_width,
+                    frame_height,
+                )
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlaybackMode {
+    Loop,
+    // Plays forward then backward without repeating the end frames
+    PingPong,
+    // Stops on the last frame
+    Once,
+}
+
+pub struct Clip {
+    pub name: String,
+    // Indices into the sprite sheet frames
+    pub frames: Vec<usize>,
+    // Seconds each frame is shown
+    pub durations: Vec<f32>,
+    pub mode: PlaybackMode,
+}
+
+impl Clip {
+    pub fn new(name: &str, frames: Vec<usize>, frame_duration: f32, mode: PlaybackMode) -> Self {
+        let durations = vec![frame_duration; frames.len()];
+        Self::with_durations(name, frames, durations, mode)
+    }
+
+    pub fn with_durations(
+        name: &str,
+        frames: Vec<usize>,
+        durations: Vec<f32>,
+        mode: PlaybackMode,
+    ) -> Self {
+        assert!(!frames.is_empty());
+        assert_eq!(frames.len(), durations.len());
+
+        Self {
+            name: name.to_string(),
+            frames,
+            durations,
+            mode,
+        }
+    }
+
+    pub fn length(&self) -> f32 {
+        self.durations.iter().sum()
+    }
+}
+
+// Index of the clip frame shown after time seconds
+pub fn clip_frame_index(durations: &[f32], mode: PlaybackMode, time: f32) -> usize {
+    let count = durations.len();
+    let length: f32 = durations.iter().sum();
+    if count <= 1 || length <= 0.0 {
+        return 0;
+    }
+
+    // Frame sequence of one period
+    let sequence: Vec<usize> = match mode {
+        PlaybackMode::PingPong => (0..count).chain((1..count - 1).rev()).collect(),
+        _ => (0..count).collect(),
+    };
+    let period: f32 = sequence.iter().map(|i| durations[*i]).sum();
+
+    let mut time = match mode {
+        PlaybackMode::Once if time >= period => return count - 1,
+        PlaybackMode::Once => time,
+        _ => time.rem_euclid(period),
+    };
+
+    for index in &sequence {
+        time -= durations[*index];
+        if time < 0.0 {
+            return *index;
+        }
+    }
+
+    *sequence.last().unwrap()
+}
+
+// Playback state of one sprite, clips are looked up by name
+pub struct SpriteAnimation {
+    pub clips: Vec<Clip>,
+    current: usize,
+    time: f32,
+}
+
+impl SpriteAnimation {
+    pub fn new(clips: Vec<Clip>) -> Self {
+        assert!(!clips.is_empty());
+
+        Self {
+            clips,
+            current: 0,
+            time: 0.0,
+        }
+    }
+
+    // Switching to the clip already playing keeps its progress,
+    // finished one shot clips start over
+    pub fn play(&mut self, name: &str) {
+        if self.clip().name == name && !self.is_finished() {
+            return;
+        }
+
+        if let Some(index) = self.clips.iter().position(|clip| clip.name == name) {
+            self.current = index;
+            self.time = 0.0;
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+    }
+
+    pub fn clip(&self) -> &Clip {
+        &self.clips[self.current]
+    }
+
+    // Sprite sheet frame to draw
+    pub fn frame(&self) -> usize {
+        let clip = self.clip();
+        clip.frames[clip_frame_index(&clip.durations, clip.mode, self.time)]
+    }
+
+    pub fn is_finished(&self) -> bool {
+        let clip = self.clip();
+        clip.mode == PlaybackMode::Once && self.time >= clip.length()
+    }
+
+    // Plays the clip matching the moving direction, one shot clips are
+    // not interrupted and standing still without an idle clip keeps the last one
+    pub fn face(&mut self, clips: &DirectionClips, direction: Vector2) {
+        if self.clip().mode == PlaybackMode::Once && !self.is_finished() {
+            return;
+        }
+
+        if let Some(name) = clips.select(direction) {
+            self.play(name);
+        }
+    }
+}
+
+// Clip names for each moving direction, up and down are optional
+// and fall back to the current horizontal facing
+pub struct DirectionClips {
+    pub left: String,
+    pub right: String,
+    pub up: Option<String>,
+    pub down: Option<String>,
+    // Played when direction is zero
+    pub idle: Option<String>,
+}
+
+impl DirectionClips {
+    pub fn horizontal(left: &str, right: &str) -> Self {
+        Self {
+            left: left.to_string(),
+            right: right.to_string(),
+            up: None,
+            down: None,
+            idle: None,
+        }
+    }
+
+    pub fn select(&self, direction: Vector2) -> Option<&str> {
+        let vertical = direction.y.abs() > direction.x.abs();
+
+        if vertical {
+            // Screen y grows downwards
+            let clip = if direction.y < 0.0 {
+                &self.up
+            } else {
+                &self.down
+            };
+            return clip.as_deref();
+        }
+
+        if direction.x < 0.0 {
+            Some(&self.left)
+        } else if direction.x > 0.0 {
+            Some(&self.right)
+        } else {
+            self.idle.as_deref()
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::constants::*;
+use crate::sprite_animation::*;
 use raylib::prelude::*;
 
 // // Just for redference, was needed for toggle_fullsreen
@@ -16,31 +17,42 @@ pub struct Ball {
     pub speed: f32,
     pub radius: f32,
     pub color: Color,
-    pub sprite: Texture2D,
+    pub sprite: SpriteSheet,
+    pub animation: SpriteAnimation,
+    pub facing: DirectionClips,
 }
 
 impl Ball {
-    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread, img: &Image) -> Self {
+    pub fn new(sprite: SpriteSheet, animation: SpriteAnimation, facing: DirectionClips) -> Self {
         Self {
             direction: Vector2::new(0f32, 0f32),
-            position: Vector2::new(SCREEN_WIDTH / 2f32, SCREEN_HEIGHT / 2f32),
+            position: Vector2::new(WORLD_WIDTH / 2f32, WORLD_HEIGHT / 2f32),
             speed: 120f32,
             radius: 5f32,
             color: Color::RED,
-            sprite: rl.load_texture_from_image(thread, img).unwrap(),
+            sprite,
+            animation,
+            facing,
         }
     }
 
-    pub fn draw(&self, d: &mut RaylibTextureMode<RaylibDrawHandle>) {
-        d.draw_texture_v(
-            &self.sprite,
-            self.position
-                - Vector2::new(
-                    self.sprite.width() as f32 / 2f32,
-                    self.sprite.height() as f32 / 2f32,
-                ),
-            self.color,
-        );
+    pub fn update_animation(&mut self, delta_time: f32) {
+        self.animation.face(&self.facing, self.direction);
+        self.animation.update(delta_time);
+    }
+
+    pub fn draw(&self, d: &mut impl RaylibDraw) {
+        self.sprite
+            .draw(d, self.animation.frame(), self.position, self.color);
+    }
+
+    // 2D world position mapped onto the 3D ground plane, 20 pixels per unit
+    pub fn world_position(&self) -> Vector3 {
+        Vector3::new(
+            (self.position.x - WORLD_WIDTH / 2f32) / 20f32,
+            0.5,
+            (self.position.y - WORLD_HEIGHT / 2f32) / 20f32,
+        )
     }
 }
 
@@ -0,0 +1,283 @@
+use crate::culling::*;
+use crate::raylib_alloc::*;
+use crate::shader::*;
+use raylib::prelude::*;
+
+// Shader define enabling texture splatting in the lighting shader
+pub const TERRAIN_DEFINE: &str = "TERRAIN";
+
+// Splat textures in material map order, bound to texture0..texture2 in fog.fs
+pub const SPLAT_MAPS: [MaterialMapIndex; 3] = [
+    MaterialMapIndex::MATERIAL_MAP_ALBEDO,
+    MaterialMapIndex::MATERIAL_MAP_METALNESS,
+    MaterialMapIndex::MATERIAL_MAP_NORMAL,
+];
+
+pub struct TerrainSettings {
+    // World size, y is the height of a white heightmap pixel
+    pub size: Vector3,
+    // Terrain is centered on x and z, y is the height of a black pixel
+    pub center: Vector3,
+    // Cells per chunk side, chunks are culled separately
+    pub chunk_cells: usize,
+    // World units one splat texture repeat covers
+    pub texture_scale: f32,
+}
+
+impl Default for TerrainSettings {
+    fn default() -> Self {
+        Self {
+            size: Vector3::new(64.0, 6.0, 64.0),
+            center: Vector3::zero(),
+            chunk_cells: 32,
+            texture_scale: 4.0,
+        }
+    }
+}
+
+pub struct TerrainChunk {
+    pub mesh: Mesh,
+    pub bounds: BoundingSphere,
+}
+
+pub struct Terrain {
+    pub settings: TerrainSettings,
+    // Heights in world units, row by row
+    pub heights: Vec<f32>,
+    pub columns: usize,
+    pub rows: usize,
+    pub chunks: Vec<TerrainChunk>,
+    pub material: WeakMaterial,
+}
+
+impl Terrain {
+    // One vertex per heightmap pixel, brightness is the height.
+    // Image can be embedded or loaded from disk with Image::load_image.
+    pub fn from_image(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        heightmap: &Image,
+        settings: TerrainSettings,
+        shader: &Shader,
+    ) -> Self {
+        let columns = heightmap.width as usize;
+        let rows = heightmap.height as usize;
+        assert!(columns >= 2 && rows >= 2);
+
+        let heights = heightmap
+            .get_image_data()
+            .iter()
+            .map(|color| {
+                let brightness = (color.r as f32 + color.g as f32 + color.b as f32) / (3.0 * 255.0);
+                settings.center.y + brightness * settings.size.y
+            })
+            .collect();
+
+        let mut material = rl.load_material_default(thread);
+        material.shader = *shader.as_ref();
+
+        let mut terrain = Self {
+            settings,
+            heights,
+            columns,
+            rows,
+            chunks: Vec::new(),
+            material,
+        };
+        terrain.build_chunks();
+        terrain
+    }
+
+    // Textures blended by the splat weights, from valleys to steep peaks
+    pub fn set_splat_textures(&mut self, textures: [&Texture2D; 3]) {
+        for (map, texture) in SPLAT_MAPS.iter().zip(textures) {
+            self.material.maps_mut()[*map as usize].texture = *texture.as_ref();
+        }
+    }
+
+    // Distance between neighbouring vertices
+    fn spacing(&self) -> Vector2 {
+        Vector2::new(
+            self.settings.size.x / (self.columns - 1) as f32,
+            self.settings.size.z / (self.rows - 1) as f32,
+        )
+    }
+
+    // World position of the first vertex
+    fn origin(&self) -> Vector2 {
+        Vector2::new(
+            self.settings.center.x - self.settings.size.x * 0.5,
+            self.settings.center.z - self.settings.size.z * 0.5,
+        )
+    }
+
+    fn height(&self, column: usize, row: usize) -> f32 {
+        self.heights[row * self.columns + column]
+    }
+
+    // Terrain height under the point, bilinearly interpolated
+    // between vertices and clamped to the terrain edge
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let origin = self.origin();
+        let spacing = self.spacing();
+
+        bilinear_height(
+            &self.heights,
+            self.columns,
+            self.rows,
+            (x - origin.x) / spacing.x,
+            (z - origin.y) / spacing.y,
+        )
+    }
+
+    // Smooth normal from central differences
+    pub fn normal(&self, column: usize, row: usize) -> Vector3 {
+        let spacing = self.spacing();
+        let left = self.height(column.saturating_sub(1), row);
+        let right = self.height((column + 1).min(self.columns - 1), row);
+        let back = self.height(column, row.saturating_sub(1));
+        let front = self.height(column, (row + 1).min(self.rows - 1));
+
+        Vector3::new(
+            (left - right) / (2.0 * spacing.x),
+            1.0,
+            (back - front) / (2.0 * spacing.y),
+        )
+        .normalized()
+    }
+
+    fn build_chunks(&mut self) {
+        let cells = self.settings.chunk_cells.max(1);
+
+        let mut row = 0;
+        while row < self.rows - 1 {
+            let mut column = 0;
+            while column < self.columns - 1 {
+                let end_column = (column + cells).min(self.columns - 1);
+                let end_row = (row + cells).min(self.rows - 1);
+                let chunk = self.build_chunk(column, row, end_column, end_row);
+                self.chunks.push(chunk);
+                column = end_column;
+            }
+            row = (row + cells).min(self.rows - 1);
+        }
+    }
+
+    // Vertices from start to end inclusive, neighbouring chunks share the edge vertices
+    fn build_chunk(
+        &self,
+        start_column: usize,
+        start_row: usize,
+        end_column: usize,
+        end_row: usize,
+    ) -> TerrainChunk {
+        let width = end_column - start_column + 1;
+        let depth = end_row - start_row + 1;
+        let vertex_count = width * depth;
+        let triangle_count = (width - 1) * (depth - 1) * 2;
+        // Meshes use 16 bit indices
+        assert!(vertex_count <= u16::MAX as usize);
+
+        let origin = self.origin();
+        let spacing = self.spacing();
+        let mut min = Vector3::new(f32::MAX, f32::MAX, f32::MAX);
+        let mut max = Vector3::new(f32::MIN, f32::MIN, f32::MIN);
+
+        let mut vertices = Vec::with_capacity(vertex_count * 3);
+        let mut texcoords = Vec::with_capacity(vertex_count * 2);
+        let mut normals = Vec::with_capacity(vertex_count * 3);
+        let mut colors = Vec::with_capacity(vertex_count * 4);
+
+        for row in start_row..=end_row {
+            for column in start_column..=end_column {
+                let position = Vector3::new(
+                    origin.x + column as f32 * spacing.x,
+                    self.height(column, row),
+                    origin.y + row as f32 * spacing.y,
+                );
+                let normal = self.normal(column, row);
+                let height = (position.y - self.settings.center.y) / self.settings.size.y;
+                let weights = splat_weights(height, normal.y);
+
+                min = min.min(position);
+                max = max.max(position);
+
+                vertices.extend([position.x, position.y, position.z]);
+                texcoords.extend([
+                    position.x / self.settings.texture_scale,
+                    position.z / self.settings.texture_scale,
+                ]);
+                normals.extend([normal.x, normal.y, normal.z]);
+                colors.extend(weights.map(|weight| (weight * 255.0).round() as u8));
+                colors.push(255);
+            }
+        }
+
+        let mut indices = Vec::with_capacity(triangle_count * 3);
+        for z in 0..depth - 1 {
+            for x in 0..width - 1 {
+                let i = (z * width + x) as u16;
+                let below = i + width as u16;
+                // Counter clockwise seen from above
+                indices.extend([i, below, i + 1, i + 1, below, below + 1]);
+            }
+        }
+
+        let mut mesh: ffi::Mesh = unsafe { std::mem::zeroed() };
+        mesh.vertexCount = vertex_count as i32;
+        mesh.triangleCount = triangle_count as i32;
+        // raylib frees mesh data itself, so it has to be allocated by raylib
+        unsafe {
+            mesh.vertices = raylib_copy(&vertices);
+            mesh.texcoords = raylib_copy(&texcoords);
+            mesh.normals = raylib_copy(&normals);
+            mesh.colors = raylib_copy(&colors);
+            mesh.indices = raylib_copy(&indices);
+            ffi::UploadMesh(&mut mesh, false);
+        }
+
+        TerrainChunk {
+            mesh: unsafe { Mesh::from_raw(mesh) },
+            bounds: BoundingSphere::from_box(&BoundingBox::new(min, max)),
+        }
+    }
+
+    pub fn draw(&self, d: &mut impl RaylibDraw3D, culling: &mut Culling) {
+        for chunk in &self.chunks {
+            if culling.test(&chunk.bounds) {
+                draw_lit_mesh(d, &chunk.mesh, &self.material, Matrix::identity());
+            }
+        }
+    }
+}
+
+// Height at fractional grid coordinates, clamped to the grid
+pub fn bilinear_height(heights: &[f32], columns: usize, rows: usize, x: f32, z: f32) -> f32 {
+    let x = x.clamp(0.0, (columns - 1) as f32);
+    let z = z.clamp(0.0, (rows - 1) as f32);
+    let x0 = (x.floor() as usize).min(columns - 2);
+    let z0 = (z.floor() as usize).min(rows - 2);
+    let fx = x - x0 as f32;
+    let fz = z - z0 as f32;
+
+    let h = |column: usize, row: usize| heights[row * columns + column];
+    let top = h(x0, z0) + (h(x0 + 1, z0) - h(x0, z0)) * fx;
+    let bottom = h(x0, z0 + 1) + (h(x0 + 1, z0 + 1) - h(x0, z0 + 1)) * fx;
+    top + (bottom - top) * fz
+}
+
+// Weights of the three splat textures, height is in [0..1] and
+// up is the y of the normal, 1.0 on flat ground. Weights sum to one.
+pub fn splat_weights(height: f32, up: f32) -> [f32; 3] {
+    let smoothstep = |edge0: f32, edge1: f32, x: f32| {
+        let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    };
+
+    // Steep slopes and peaks are rock, the rest goes from ground to grass with height
+    let rock = smoothstep(0.85, 0.7, up).max(smoothstep(0.65, 0.85, height));
+    let grass = smoothstep(0.15, 0.35, height) * (1.0 - rock);
+    let ground = 1.0 - rock - grass;
+
+    [ground, grass, rock]
+}
@@ -0,0 +1,675 @@
+use raylib::prelude::*;
+use serde::Deserialize;
+
+// Tiled stores flip flags in the highest bits of tile ids
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const ROTATED_HEXAGONAL: u32 = 0x1000_0000;
+const TILE_ID_MASK: u32 =
+    !(FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL);
+
+// Tile layers with this bool property set block movement
+pub const COLLISION_PROPERTY: &str = "collision";
+// Object type of spawn points
+pub const SPAWN_TYPE: &str = "spawn";
+
+/* Map data, same for TMX and JSON maps */
+
+#[derive(Debug, Default, PartialEq)]
+pub struct MapData {
+    pub width: i32,
+    pub height: i32,
+    pub tile_width: i32,
+    pub tile_height: i32,
+    pub tilesets: Vec<TilesetData>,
+    pub layers: Vec<TileLayer>,
+    pub objects: Vec<MapObject>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct TilesetData {
+    pub first_gid: u32,
+    pub image: String,
+    pub tile_width: i32,
+    pub tile_height: i32,
+    pub columns: i32,
+    pub tile_count: u32,
+    pub margin: i32,
+    pub spacing: i32,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct TileLayer {
+    pub name: String,
+    pub visible: bool,
+    pub collision: bool,
+    // Global tile ids row by row, 0 is an empty cell
+    pub tiles: Vec<u32>,
+}
+
+// Objects of all object layers, spawn points and triggers for gameplay
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MapObject {
+    pub name: String,
+    // Tiled "type", called "class" since Tiled 1.9
+    pub kind: String,
+    pub x: f32,
+    pub y: f32,
+}
+
+impl MapObject {
+    pub fn position(&self) -> Vector2 {
+        Vector2::new(self.x, self.y)
+    }
+}
+
+impl MapData {
+    // Format is detected from the content, TMX is XML and JSON starts with an object
+    pub fn parse(source: &str) -> Result<Self, String> {
+        if source.trim_start().starts_with('<') {
+            Self::from_tmx(source)
+        } else {
+            Self::from_json(source)
+        }
+    }
+
+    pub fn from_json(source: &str) -> Result<Self, String> {
+        let map: JsonMap = serde_json::from_str(source).map_err(|e| e.to_string())?;
+        if map.infinite {
+            return Err("infinite maps are not supported".to_string());
+        }
+
+        let mut data = MapData {
+            width: map.width,
+            height: map.height,
+            tile_width: map.tilewidth,
+            tile_height: map.tileheight,
+            ..Default::default()
+        };
+
+        for tileset in map.tilesets {
+            let image = tileset
+                .image
+                .ok_or("external tilesets are not supported, embed them in the map")?;
+            data.tilesets.push(TilesetData {
+                first_gid: tileset.firstgid,
+                image,
+                tile_width: tileset.tilewidth,
+                tile_height: tileset.tileheight,
+                columns: tileset.columns,
+                tile_count: tileset.tilecount,
+                margin: tileset.margin,
+                spacing: tileset.spacing,
+            });
+        }
+
+        for layer in map.layers {
+            match layer.kind.as_str() {
+                "tilelayer" => {
+                    let collision = layer.properties.iter().any(|property| {
+                        property.name == COLLISION_PROPERTY && property.value == true
+                    });
+                    data.layers.push(TileLayer {
+                        name: layer.name,
+                        visible: layer.visible,
+                        collision,
+                        tiles: layer
+                            .data
+                            .ok_or("tile layer data must be stored as CSV array")?,
+                    });
+                }
+                "objectgroup" => {
+                    data.objects
+                        .extend(layer.objects.into_iter().map(|object| MapObject {
+                            name: object.name,
+                            kind: if object.kind.is_empty() {
+                                object.class
+                            } else {
+                                object.kind
+                            },
+                            x: object.x,
+                            y: object.y,
+                        }));
+                }
+                // Image layers and groups are skipped
+                _ => {}
+            }
+        }
+
+        data.validate()
+    }
+
+    pub fn from_tmx(source: &str) -> Result<Self, String> {
+        let document = roxmltree::Document::parse(source).map_err(|e| e.to_string())?;
+        let map = document.root_element();
+        if attribute(&map, "infinite", 0) != 0 {
+            return Err("infinite maps are not supported".to_string());
+        }
+
+        let mut data = MapData {
+            width: attribute(&map, "width", 0),
+            height: attribute(&map, "height", 0),
+            tile_width: attribute(&map, "tilewidth", 0),
+            tile_height: attribute(&map, "tileheight", 0),
+            ..Default::default()
+        };
+
+        for node in map.children().filter(|node| node.is_element()) {
+            match node.tag_name().name() {
+                "tileset" => {
+                    let image = node
+                        .children()
+                        .find(|child| child.has_tag_name("image"))
+                        .and_then(|image| image.attribute("source"))
+                        .ok_or("external tilesets are not supported, embed them in the map")?;
+                    data.tilesets.push(TilesetData {
+                        first_gid: attribute(&node, "firstgid", 1),
+                        image: image.to_string(),
+                        tile_width: attribute(&node, "tilewidth", 0),
+                        tile_height: attribute(&node, "tileheight", 0),
+                        columns: attribute(&node, "columns", 0),
+                        tile_count: attribute(&node, "tilecount", 0),
+                        margin: attribute(&node, "margin", 0),
+                        spacing: attribute(&node, "spacing", 0),
+                    });
+                }
+                "layer" => {
+                    let tile_data = node
+                        .children()
+                        .find(|child| child.has_tag_name("data"))
+                        .ok_or("tile layer has no data")?;
+                    if tile_data.attribute("encoding") != Some("csv") {
+                        return Err("tile layer data must be stored as CSV".to_string());
+                    }
+
+                    let tiles = tile_data
+                        .text()
+                        .unwrap_or("")
+                        .split(',')
+                        .map(|id| id.trim().parse::<u32>().map_err(|e| e.to_string()))
+                        .collect::<Result<Vec<u32>, String>>()?;
+
+                    let collision = node
+                        .descendants()
+                        .filter(|child| child.has_tag_name("property"))
+                        .any(|property| {
+                            property.attribute("name") == Some(COLLISION_PROPERTY)
+                                && property.attribute("value") == Some("true")
+                        });
+
+                    data.layers.push(TileLayer {
+                        name: node.attribute("name").unwrap_or("").to_string(),
+                        visible: attribute(&node, "visible", 1) != 0,
+                        collision,
+                        tiles,
+                    });
+                }
+                "objectgroup" => {
+                    for object in node.children().filter(|child| child.has_tag_name("object")) {
+                        let kind = object
+                            .attribute("type")
+                            .or(object.attribute("class"))
+                            .unwrap_or("");
+                        data.objects.push(MapObject {
+                            name: object.attribute("name").unwrap_or("").to_string(),
+                            kind: kind.to_string(),
+                            x: attribute(&object, "x", 0.0),
+                            y: attribute(&object, "y", 0.0),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        data.validate()
+    }
+
+    fn validate(self) -> Result<Self, String> {
+        if self.width <= 0 || self.height <= 0 || self.tile_width <= 0 || self.tile_height <= 0 {
+            return Err("map and tile size must be positive".to_string());
+        }
+
+        let cells = (self.width * self.height) as usize;
+        if let Some(layer) = self.layers.iter().find(|layer| layer.tiles.len() != cells) {
+            return Err(format!("layer '{}' doesn't match the map size", layer.name));
+        }
+
+        Ok(self)
+    }
+}
+
+// Source flips and clockwise rotation in degrees that draw a tile like Tiled.
+// Tiled swaps x and y for the diagonal flag before flipping, which is the
+// same as a vertically flipped source rotated by 90 degrees.
+pub fn tile_orientation(gid: u32) -> (bool, bool, f32) {
+    let horizontal = gid & FLIPPED_HORIZONTALLY != 0;
+    let vertical = gid & FLIPPED_VERTICALLY != 0;
+
+    if gid & FLIPPED_DIAGONALLY != 0 {
+        // Rotation turns the later flips by 90 degrees, so they switch axes
+        (vertical, !horizontal, 90.0)
+    } else {
+        (horizontal, vertical, 0.0)
+    }
+}
+
+fn attribute<T: std::str::FromStr>(node: &roxmltree::Node, name: &str, default: T) -> T {
+    node.attribute(name)
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/* Tiled JSON format, only fields used by the loader */
+
+#[derive(Deserialize)]
+struct JsonMap {
+    width: i32,
+    height: i32,
+    tilewidth: i32,
+    tileheight: i32,
+    #[serde(default)]
+    infinite: bool,
+    #[serde(default)]
+    tilesets: Vec<JsonTileset>,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+}
+
+#[derive(Deserialize)]
+struct JsonTileset {
+    firstgid: u32,
+    image: Option<String>,
+    #[serde(default)]
+    tilewidth: i32,
+    #[serde(default)]
+    tileheight: i32,
+    #[serde(default)]
+    columns: i32,
+    #[serde(default)]
+    tilecount: u32,
+    #[serde(default)]
+    margin: i32,
+    #[serde(default)]
+    spacing: i32,
+}
+
+#[derive(Deserialize)]
+struct JsonLayer {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default = "default_visible")]
+    visible: bool,
+    data: Option<Vec<u32>>,
+    #[serde(default)]
+    objects: Vec<JsonObject>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct JsonObject {
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type")]
+    kind: String,
+    #[serde(default)]
+    class: String,
+    x: f32,
+    y: f32,
+}
+
+#[derive(Deserialize)]
+struct JsonProperty {
+    name: String,
+    value: serde_json::Value,
+}
+
+/* Runtime map */
+
+pub struct Tileset {
+    pub data: TilesetData,
+    pub texture: Texture2D,
+}
+
+impl Tileset {
+    // Source rectangle of a tile id local to this tileset
+    pub fn tile_rect(&self, local_id: u32) -> Rectangle {
+        let data = &self.data;
+        let columns = data.columns.max(1) as u32;
+        let x = data.margin + (local_id % columns) as i32 * (data.tile_width + data.spacing);
+        let y = data.margin + (local_id / columns) as i32 * (data.tile_height + data.spacing);
+
+        Rectangle::new(
+            x as f32,
+            y as f32,
+            data.tile_width as f32,
+            data.tile_height as f32,
+        )
+    }
+}
+
+pub struct Tilemap {
+    pub width: i32,
+    pub height: i32,
+    pub tile_width: i32,
+    pub tile_height: i32,
+    pub layers: Vec<TileLayer>,
+    pub objects: Vec<MapObject>,
+    // Sorted by first_gid
+    pub tilesets: Vec<Tileset>,
+}
+
+impl Tilemap {
+    // Tileset images are referenced by path, image_data returns the file content for it
+    pub fn load(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        source: &str,
+        image_data: impl Fn(&str) -> Option<&'static [u8]>,
+    ) -> Result<Self, String> {
+        let data = MapData::parse(source)?;
+
+        let mut tilesets = Vec::with_capacity(data.tilesets.len());
+        for tileset in data.tilesets {
+            let bytes = image_data(&tileset.image)
+                .ok_or(format!("missing tileset image '{}'", tileset.image))?;
+            let extension = tileset
+                .image
+                .rfind('.')
+                .map_or(".png", |i| &tileset.image[i..]);
+
+            let img = Image::load_image_from_mem(extension, bytes)?;
+            let texture = rl.load_texture_from_image(thread, &img)?;
+            // Neighbouring tiles would bleed in with bilinear filtering
+            texture.set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_POINT);
+
+            tilesets.push(Tileset {
+                data: tileset,
+                texture,
+            });
+        }
+        tilesets.sort_by_key(|tileset| tileset.data.first_gid);
+
+        Ok(Self {
+            width: data.width,
+            height: data.height,
+            tile_width: data.tile_width,
+            tile_height: data.tile_height,
+            layers: data.layers,
+            objects: data.objects,
+            tilesets,
+        })
+    }
+
+    // Map area in pixels
+    pub fn bounds(&self) -> Rectangle {
+        Rectangle::new(
+            0.0,
+            0.0,
+            (self.width * self.tile_width) as f32,
+            (self.height * self.tile_height) as f32,
+        )
+    }
+
+    // Position of the spawn point object with the name
+    pub fn spawn(&self, name: &str) -> Option<Vector2> {
+        self.objects
+            .iter()
+            .find(|object| object.kind == SPAWN_TYPE && object.name == name)
+            .map(MapObject::position)
+    }
+
+    // Tileset containing the global tile id, ids past the last tile have none
+    fn tileset(&self, gid: u32) -> Option<&Tileset> {
+        self.tilesets
+            .iter()
+            .rev()
+            .find(|tileset| tileset.data.first_gid <= gid)
+            .filter(|tileset| gid - tileset.data.first_gid < tileset.data.tile_count)
+    }
+
+    // Range of tiles overlapping the area, clamped to the map
+    pub fn tile_range(&self, area: Rectangle) -> (i32, i32, i32, i32) {
+        let start_x = ((area.x / self.tile_width as f32).floor() as i32).max(0);
+        let start_y = ((area.y / self.tile_height as f32).floor() as i32).max(0);
+        let end_x =
+            (((area.x + area.width) / self.tile_width as f32).ceil() as i32).min(self.width);
+        let end_y =
+            (((area.y + area.height) / self.tile_height as f32).ceil() as i32).min(self.height);
+
+        (start_x, start_y, end_x, end_y)
+    }
+
+    // Draws visible layers, only tiles inside view are submitted
+    pub fn draw(&self, d: &mut impl RaylibDraw, view: Rectangle) {
+        let (start_x, start_y, end_x, end_y) = self.tile_range(view);
+
+        for layer in self.layers.iter().filter(|layer| layer.visible) {
+            for y in start_y..end_y {
+                for x in start_x..end_x {
+                    let gid = layer.tiles[(y * self.width + x) as usize];
+                    let id = gid & TILE_ID_MASK;
+                    if id == 0 {
+                        continue;
+                    }
+
+                    let Some(tileset) = self.tileset(id) else {
+                        continue;
+                    };
+
+                    // Negative source size flips the tile
+                    let mut source = tileset.tile_rect(id - tileset.data.first_gid);
+                    let (flip_x, flip_y, rotation) = tile_orientation(gid);
+                    if flip_x {
+                        source.width = -source.width;
+                    }
+                    if flip_y {
+                        source.height = -source.height;
+                    }
+
+                    // Tiles larger than the grid are aligned to the cell bottom, like in Tiled.
+                    // Rotation is around the tile center.
+                    let (width, height) = (source.width.abs(), source.height.abs());
+                    let dest = Rectangle::new(
+                        (x * self.tile_width) as f32 + width * 0.5,
+                        ((y + 1) * self.tile_height) as f32 - height * 0.5,
+                        width,
+                        height,
+                    );
+                    let origin = Vector2::new(width * 0.5, height * 0.5);
+                    d.draw_texture_pro(
+                        &tileset.texture,
+                        source,
+                        dest,
+                        origin,
+                        rotation,
+                        Color::WHITE,
+                    );
+                }
+            }
+        }
+    }
+
+    // Cells outside of the map are solid
+    pub fn is_solid(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return true;
+        }
+
+        let index = (y * self.width + x) as usize;
+        self.layers
+            .iter()
+            .filter(|layer| layer.collision)
+            .any(|layer| layer.tiles[index] & TILE_ID_MASK != 0)
+    }
+
+    pub fn collides_circle(&self, center: Vector2, radius: f32) -> bool {
+        let area = Rectangle::new(
+            center.x - radius,
+            center.y - radius,
+            radius * 2.0,
+            radius * 2.0,
+        );
+        // Not clamped to the map, so cells outside of it count as solid
+        let start_x = (area.x / self.tile_width as f32).floor() as i32;
+        let start_y = (area.y / self.tile_height as f32).floor() as i32;
+        let end_x = ((area.x + area.width) / self.tile_width as f32).ceil() as i32;
+        let end_y = ((area.y + area.height) / self.tile_height as f32).ceil() as i32;
+
+        for y in start_y..end_y {
+            for x in start_x..end_x {
+                if !self.is_solid(x, y) {
+                    continue;
+                }
+
+                let cell = Rectangle::new(
+                    (x * self.tile_width) as f32,
+                    (y * self.tile_height) as f32,
+                    self.tile_width as f32,
+                    self.tile_height as f32,
+                );
+                if cell.check_collision_circle_rec(center, radius) {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON_MAP: &str = r#"{
+        "width": 3, "height": 2, "tilewidth": 16, "tileheight": 16,
+        "tilesets": [{
+            "firstgid": 1, "image": "tiles.png", "tilewidth": 16, "tileheight": 16,
+            "columns": 4, "tilecount": 8, "margin": 1, "spacing": 2
+        }],
+        "layers": [
+            {"type": "tilelayer", "name": "ground", "data": [1, 0, 2, 0, 0, 3]},
+            {
+                "type": "tilelayer", "name": "walls", "visible": false,
+                "data": [0, 0, 0, 4, 2147483653, 3758096390],
+                "properties": [{"name": "collision", "type": "bool", "value": true}]
+            },
+            {"type": "objectgroup", "name": "objects", "objects": [
+                {"name": "player", "type": "spawn", "x": 8.0, "y": 24.0},
+                {"name": "exit", "class": "trigger", "x": 40.0, "y": 8.0}
+            ]}
+        ]
+    }"#;
+
+    const TMX_MAP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" width="3" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <tileset firstgid="1" name="tiles" tilewidth="16" tileheight="16" spacing="2" margin="1"
+  tilecount="8" columns="4">
+  <image source="tiles.png" width="72" height="38"/>
+ </tileset>
+ <layer id="1" name="ground" width="3" height="2">
+  <data encoding="csv">
+1,0,2,
+0,0,3
+</data>
+ </layer>
+ <layer id="2" name="walls" width="3" height="2" visible="0">
+  <properties>
+   <property name="collision" type="bool" value="true"/>
+  </properties>
+  <data encoding="csv">
+0,0,0,
+4,2147483653,3758096390
+</data>
+ </layer>
+ <objectgroup id="3" name="objects">
+  <object id="1" name="player" type="spawn" x="8" y="24"/>
+  <object id="2" name="exit" class="trigger" x="40" y="8"/>
+ </objectgroup>
+</map>"#;
+
+    // Map without tilesets, collision doesn't need textures
+    fn tilemap(data: MapData) -> Tilemap {
+        Tilemap {
+            width: data.width,
+            height: data.height,
+            tile_width: data.tile_width,
+            tile_height: data.tile_height,
+            layers: data.layers,
+            objects: data.objects,
+            tilesets: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn json_and_tmx_maps_are_the_same() {
+        let json = MapData::parse(JSON_MAP).unwrap();
+        let tmx = MapData::parse(TMX_MAP).unwrap();
+        assert_eq!(json, tmx);
+
+        assert_eq!(json.tilesets[0].margin, 1);
+        assert_eq!(json.tilesets[0].spacing, 2);
+        assert!(json.layers[0].visible && !json.layers[0].collision);
+        assert!(!json.layers[1].visible && json.layers[1].collision);
+        assert_eq!(json.objects[1].kind, "trigger");
+    }
+
+    #[test]
+    fn flip_flags_are_decoded() {
+        let map = MapData::parse(JSON_MAP).unwrap();
+        let tiles = &map.layers[1].tiles;
+
+        assert_eq!(tiles[4], FLIPPED_HORIZONTALLY | 5);
+        assert_eq!(tiles[4] & TILE_ID_MASK, 5);
+        assert_eq!(
+            tiles[5],
+            FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | 6
+        );
+        assert_eq!(tiles[5] & TILE_ID_MASK, 6);
+    }
+
+    #[test]
+    fn tile_orientation_matches_tiled() {
+        assert_eq!(tile_orientation(1), (false, false, 0.0));
+        assert_eq!(
+            tile_orientation(FLIPPED_HORIZONTALLY | 1),
+            (true, false, 0.0)
+        );
+        assert_eq!(tile_orientation(FLIPPED_VERTICALLY | 1), (false, true, 0.0));
+        // Swapping x and y
+        assert_eq!(
+            tile_orientation(FLIPPED_DIAGONALLY | 1),
+            (false, true, 90.0)
+        );
+        // Tiled rotates clockwise with diagonal and horizontal flips
+        let clockwise = FLIPPED_DIAGONALLY | FLIPPED_HORIZONTALLY | 1;
+        assert_eq!(tile_orientation(clockwise), (false, false, 90.0));
+        // and counter clockwise with diagonal and vertical flips
+        let counter_clockwise = FLIPPED_DIAGONALLY | FLIPPED_VERTICALLY | 1;
+        assert_eq!(tile_orientation(counter_clockwise), (true, true, 90.0));
+    }
+
+    #[test]
+    fn circle_collides_with_solid_cells() {
+        let map = tilemap(MapData::parse(JSON_MAP).unwrap());
+
+        // Top row of the walls layer is empty, ground tiles don't collide
+        assert!(!map.collides_circle(Vector2::new(8.0, 8.0), 4.0));
+        assert!(!map.collides_circle(Vector2::new(24.0, 8.0), 6.0));
+        // Reaching into the walls below
+        assert!(map.collides_circle(Vector2::new(24.0, 13.0), 4.0));
+        assert!(map.collides_circle(Vector2::new(8.0, 24.0), 2.0));
+        // Outside of the map is solid
+        assert!(map.collides_circle(Vector2::new(8.0, 2.0), 4.0));
+        assert!(map.collides_circle(Vector2::new(-10.0, 8.0), 4.0));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::constants::*;
+use crate::preprocessor::*;
+use crate::retro::*;
+use crate::shader::*;
+use raylib::prelude::*;
+
+// Last post processing pass. The scene is lit, fogged and bloomed in linear
+// HDR, this maps it to display colors once for everything drawn into it.
+pub struct ToneMapping {
+    pub tone_mapper: ToneMapper,
+    // Scales the linear scene color before tone mapping
+    pub exposure: f32,
+    // Display colors, 2D layers are drawn on top of it
+    pub output: RenderTexture2D,
+    // Quantizes the pixels of retro materials
+    palette: Option<RetroPalette>,
+    shader: Shader,
+    tone_mapper_loc: i32,
+    exposure_loc: i32,
+    palette_loc: i32,
+}
+
+impl ToneMapping {
+    pub fn new(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        width: u32,
+        height: u32,
+        tone_mapper: ToneMapper,
+        exposure: f32,
+    ) -> Self {
+        let tone_map_shader = shader_source(TONE_MAP_SHADER, ShaderStage::Fragment, &[]);
+        let shader = rl.load_shader_from_memory(thread, None, Some(&tone_map_shader));
+
+        Self {
+            tone_mapper,
+            exposure,
+            output: rl.load_render_texture(thread, width, height).unwrap(),
+            tone_mapper_loc: shader.get_shader_location("toneMapper"),
+            exposure_loc: shader.get_shader_location("exposure"),
+            palette_loc: shader.get_shader_location("palette"),
+            palette: None,
+            shader,
+        }
+    }
+
+    // Palette and dithering of the retro materials
+    pub fn set_retro_palette(&mut self, palette: RetroPalette, settings: &RetroSettings) {
+        let palette_size_loc = self.shader.get_shader_location("paletteSize");
+        self.shader.set_shader_value(palette_size_loc, palette.size);
+
+        let dither_strength_loc = self.shader.get_shader_location("ditherStrength");
+        self.shader
+            .set_shader_value(dither_strength_loc, settings.dither_strength);
+
+        self.palette = Some(palette);
+    }
+
+    // Tone maps the scene into output
+    pub fn apply(
+        &mut self,
+        d: &mut RaylibDrawHandle,
+        thread: &RaylibThread,
+        scene: &RenderTexture2D,
+    ) {
+        self.shader
+            .set_shader_value(self.tone_mapper_loc, self.tone_mapper as i32);
+        self.shader
+            .set_shader_value(self.exposure_loc, self.exposure);
+
+        let shader = *self.shader.as_ref();
+        let mut d = d.begin_texture_mode(thread, &mut self.output);
+        d.clear_background(Color::BLACK);
+        let mut d = d.begin_shader_mode(&shader);
+        // NOTE: Additional samplers have to be set after shader mode begins
+        if let Some(palette) = &self.palette {
+            self.shader
+                .set_shader_value_texture(self.palette_loc, &palette.texture);
+        }
+        // Render textures are stored upside down, negative source height flips them back
+        d.draw_texture_pro(
+            scene.texture(),
+            Rectangle::new(
+                0f32,
+                0f32,
+                scene.texture.width as f32,
+                -scene.texture.height as f32,
+            ),
+            Rectangle::new(
+                0f32,
+                0f32,
+                scene.texture.width as f32,
+                scene.texture.height as f32,
+            ),
+            Vector2::zero(),
+            0f32,
+            Color::WHITE,
+        );
+    }
+}
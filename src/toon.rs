@@ -0,0 +1,186 @@
+use crate::constants::*;
+use crate::fog::*;
+use crate::shader::*;
+use raylib::prelude::*;
+
+// Shader defines selecting a lighting model of the lighting shader,
+// without either of them it lights like before
+pub const TOON_DEFINE: &str = "TOON";
+pub const RIM_DEFINE: &str = "RIM";
+// Shader define turning the lighting shader into the outline hull shader
+pub const OUTLINE_DEFINE: &str = "OUTLINE";
+
+// Ramp borrows the unused occlusion map slot, like the retro palette
+pub const TOON_RAMP_MAP: MaterialMapIndex = MaterialMapIndex::MATERIAL_MAP_OCCLUSION;
+const TOON_RAMP_LOC: ShaderLocationIndex = ShaderLocationIndex::SHADER_LOC_MAP_OCCLUSION;
+
+// Light levels of the toon ramp from the unlit back to facing the light.
+// The back half stays dark, the lit half has a shade band and full light.
+pub const DEFAULT_TOON_BANDS: [f32; 4] = [0.0, 0.0, 0.35, 1.0];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LightingModel {
+    Phong,
+    Toon,
+    Rim,
+}
+
+impl LightingModel {
+    pub fn next(self) -> Self {
+        match self {
+            LightingModel::Phong => LightingModel::Toon,
+            LightingModel::Toon => LightingModel::Rim,
+            LightingModel::Rim => LightingModel::Phong,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LightingModel::Phong => "Phong",
+            LightingModel::Toon => "toon",
+            LightingModel::Rim => "rim",
+        }
+    }
+}
+
+pub struct ToonRamp {
+    pub texture: Texture2D,
+}
+
+impl ToonRamp {
+    // Stores the light levels in a (bands x 1) texture. Point filtering keeps
+    // the steps hard, clamping keeps the edges from wrapping around.
+    pub fn new(rl: &mut RaylibHandle, thread: &RaylibThread, bands: &[f32]) -> Self {
+        let mut img = Image::gen_image_color(bands.len().max(1) as i32, 1, Color::BLACK);
+        for (i, level) in bands.iter().enumerate() {
+            let level = (level.clamp(0.0, 1.0) * 255.0).round() as u8;
+            img.draw_pixel(i as i32, 0, Color::new(level, level, level, 255));
+        }
+
+        let texture = rl.load_texture_from_image(thread, &img).unwrap();
+        texture.set_texture_filter(thread, TextureFilter::TEXTURE_FILTER_POINT);
+        texture.set_texture_wrap(thread, TextureWrap::TEXTURE_WRAP_CLAMP);
+
+        Self { texture }
+    }
+}
+
+// Sets up the ramp location of a shader loaded with TOON_DEFINE
+pub fn setup_toon_shader(shader: &mut Shader) {
+    shader.locs_mut()[TOON_RAMP_LOC as usize] = shader.get_shader_location("toonRamp");
+}
+
+pub struct RimSettings {
+    pub color: Color,
+    // Higher powers make a thinner rim
+    pub power: f32,
+    pub strength: f32,
+}
+
+impl Default for RimSettings {
+    fn default() -> Self {
+        Self {
+            color: Color::new(200, 220, 255, 255),
+            power: 3.0,
+            strength: 1.5,
+        }
+    }
+}
+
+// Sets up rim uniforms of a shader loaded with RIM_DEFINE
+pub fn setup_rim_shader(shader: &mut Shader, settings: &RimSettings) {
+    let color: Vector4 = settings.color.color_normalize();
+    let rim_color_loc = shader.get_shader_location("rimColor");
+    shader.set_shader_value(
+        rim_color_loc,
+        Vector4::new(color.x, color.y, color.z, settings.strength),
+    );
+
+    let rim_power_loc = shader.get_shader_location("rimPower");
+    shader.set_shader_value(rim_power_loc, settings.power);
+}
+
+// Switches a material to the shader of a lighting model, only toon shading samples the ramp
+pub fn apply_lighting_model(material: &mut WeakMaterial, shader: &Shader, ramp: &ToonRamp) {
+    material.shader = *shader.as_ref();
+    material.maps_mut()[TOON_RAMP_MAP as usize].texture = *ramp.texture.as_ref();
+}
+
+pub struct OutlineSettings {
+    // In render target pixels
+    pub width: f32,
+    pub color: Color,
+}
+
+impl Default for OutlineSettings {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            color: Color::new(20, 16, 24, 255),
+        }
+    }
+}
+
+// Inverted hull outlines. The mesh is drawn again pushed out along its
+// normals with front faces culled, so only the rim around it stays visible.
+// Meshes with split normals on hard edges get gaps in the outline at corners.
+pub struct Outline {
+    // Gets fog like the other lighting shaders
+    pub shader: LightingShader,
+    pub material: WeakMaterial,
+    width_loc: i32,
+}
+
+impl Outline {
+    pub fn new(
+        rl: &mut RaylibHandle,
+        thread: &RaylibThread,
+        settings: &OutlineSettings,
+        fog: &AnimatedFog,
+    ) -> Self {
+        let mut shader = LightingShader::load(rl, thread, &[OUTLINE_DEFINE]);
+        let resolution_loc = shader.shader.get_shader_location("resolution");
+        shader
+            .shader
+            .set_shader_value(resolution_loc, Vector2::new(SCREEN_WIDTH, SCREEN_HEIGHT));
+
+        let mut material = rl.load_material_default(thread);
+        material.shader = *shader.shader.as_ref();
+        fog.attach(&mut material);
+
+        let mut outline = Self {
+            width_loc: shader.shader.get_shader_location("outlineWidth"),
+            shader,
+            material,
+        };
+        outline.set_width(settings.width);
+        outline.set_color(settings.color);
+        outline
+    }
+
+    pub fn set_width(&mut self, width: f32) {
+        self.shader.shader.set_shader_value(self.width_loc, width);
+    }
+
+    // Outline color is the material diffuse color, which raylib uploads itself
+    pub fn set_color(&mut self, color: Color) {
+        self.material.maps_mut()[MaterialMapIndex::MATERIAL_MAP_ALBEDO as usize].color =
+            color.into();
+    }
+
+    // Draw together with the model, depth testing hides the hull behind it
+    pub fn draw_model(&self, d: &mut impl RaylibDraw3D, model: &Model, transform: Matrix) {
+        let transform = *model.transform() * transform;
+
+        unsafe {
+            ffi::rlDrawRenderBatchActive();
+            ffi::rlSetCullFace(ffi::rlCullMode::RL_CULL_FACE_FRONT as i32);
+        }
+        for mesh in model.meshes() {
+            draw_lit_mesh(d, mesh, &self.material, transform);
+        }
+        unsafe {
+            ffi::rlSetCullFace(ffi::rlCullMode::RL_CULL_FACE_BACK as i32);
+        }
+    }
+}
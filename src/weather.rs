@@ -0,0 +1,348 @@
+use crate::particles::*;
+use raylib::prelude::*;
+use serde::Deserialize;
+
+// Weather at one moment, every field is interpolated when the weather changes.
+// Missing fields in scene files fall back to a clear day.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct WeatherState {
+    // Fraction of the rain drops falling, 0.0 is dry
+    pub rain: f32,
+    // Fraction of the snowflakes falling
+    pub snow: f32,
+    // Horizontal wind pushing drops and flakes, in world units per second
+    pub wind: (f32, f32),
+    // Multipliers of the time of day values
+    pub fog_density: f32,
+    pub ambient: f32,
+    pub light: f32,
+}
+
+impl Default for WeatherState {
+    fn default() -> Self {
+        Self::CLEAR
+    }
+}
+
+impl WeatherState {
+    pub const CLEAR: Self = Self {
+        rain: 0.0,
+        snow: 0.0,
+        wind: (0.0, 0.0),
+        fog_density: 1.0,
+        ambient: 1.0,
+        light: 1.0,
+    };
+    pub const RAIN: Self = Self {
+        rain: 1.0,
+        snow: 0.0,
+        wind: (1.5, 0.5),
+        fog_density: 1.8,
+        ambient: 0.8,
+        light: 0.35,
+    };
+    pub const SNOW: Self = Self {
+        rain: 0.0,
+        snow: 1.0,
+        wind: (0.6, 0.2),
+        fog_density: 2.2,
+        ambient: 1.3,
+        light: 0.5,
+    };
+
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let lerp = |a: f32, b: f32| a + (b - a) * t;
+        Self {
+            rain: lerp(self.rain, other.rain),
+            snow: lerp(self.snow, other.snow),
+            wind: (
+                lerp(self.wind.0, other.wind.0),
+                lerp(self.wind.1, other.wind.1),
+            ),
+            fog_density: lerp(self.fog_density, other.fog_density),
+            ambient: lerp(self.ambient, other.ambient),
+            light: lerp(self.light, other.light),
+        }
+    }
+}
+
+// Weather held for a while after blending into it
+#[derive(Clone, Debug, Deserialize)]
+pub struct WeatherStep {
+    // Seconds spent blending from the previous weather
+    #[serde(default)]
+    pub transition: f32,
+    // Seconds the weather stays before the next step starts
+    #[serde(default)]
+    pub hold: f32,
+    #[serde(flatten)]
+    pub state: WeatherState,
+}
+
+// Sequence of weather steps, the last one stays unless the script loops
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct WeatherScript {
+    #[serde(default)]
+    pub looping: bool,
+    pub steps: Vec<WeatherStep>,
+}
+
+// Settings stored in scene files, sections other systems don't know are ignored
+#[derive(Debug, Default, Deserialize)]
+pub struct SceneFile {
+    #[serde(default)]
+    pub weather: WeatherScript,
+}
+
+impl SceneFile {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        serde_json::from_str(source).map_err(|e| e.to_string())
+    }
+}
+
+// Current weather, either following a script or set by hand
+pub struct Weather {
+    pub script: WeatherScript,
+    // Scripted changes stop once the weather is set by hand
+    pub scripted: bool,
+    step: usize,
+    from: WeatherState,
+    to: WeatherState,
+    transition: f32,
+    hold: f32,
+    elapsed: f32,
+    current: WeatherState,
+}
+
+impl Weather {
+    pub fn new(script: WeatherScript) -> Self {
+        let mut weather = Self {
+            script,
+            scripted: true,
+            step: 0,
+            from: WeatherState::CLEAR,
+            to: WeatherState::CLEAR,
+            transition: 0.0,
+            hold: 0.0,
+            elapsed: 0.0,
+            current: WeatherState::CLEAR,
+        };
+        // First step is the starting weather, its transition is skipped
+        if let Some(step) = weather.script.steps.first().cloned() {
+            weather.blend_to(step.state, 0.0, step.hold);
+        }
+        weather
+    }
+
+    pub fn current(&self) -> &WeatherState {
+        &self.current
+    }
+
+    // Target weather of the running change
+    pub fn target(&self) -> &WeatherState {
+        &self.to
+    }
+
+    // Blends from the current weather to the state, stops the script
+    pub fn set(&mut self, state: WeatherState, transition: f32) {
+        self.scripted = false;
+        self.blend_to(state, transition, 0.0);
+    }
+
+    fn blend_to(&mut self, state: WeatherState, transition: f32, hold: f32) {
+        self.from = self.current;
+        self.to = state;
+        self.transition = transition;
+        self.hold = hold;
+        self.elapsed = 0.0;
+        if transition <= 0.0 {
+            self.current = state;
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.elapsed += delta_time;
+
+        let t = if self.transition > 0.0 {
+            (self.elapsed / self.transition).min(1.0)
+        } else {
+            1.0
+        };
+        // Smoothstep, changes ease in and out
+        self.current = self.from.lerp(&self.to, t * t * (3.0 - 2.0 * t));
+
+        if !self.scripted || self.elapsed < self.transition + self.hold {
+            return;
+        }
+
+        let next = self.step + 1;
+        let next = if next < self.script.steps.len() {
+            next
+        } else if self.script.looping {
+            0
+        } else {
+            return;
+        };
+
+        let step = self.script.steps[next].clone();
+        self.step = next;
+        self.blend_to(step.state, step.transition, step.hold);
+    }
+}
+
+// Rain drops or snowflakes in a box following the camera. Positions wrap
+// around the box, so nothing is ever spawned and the pool never changes.
+pub struct Precipitation {
+    // Size of the box around the camera
+    pub area: Vector3,
+    // Falling velocity without wind
+    pub fall: Vector3,
+    // Rain is drawn as streaks this many seconds of movement long,
+    // 0.0 draws billboards instead
+    pub streak: f32,
+    // Sideways swaying of snowflakes in world units
+    pub sway: f32,
+    pub size: f32,
+    pub color: Color,
+    positions: Vec<Vector3>,
+    // Fraction of the pool drawn, the weather intensity
+    amount: f32,
+    wind: Vector3,
+    time: f32,
+}
+
+impl Precipitation {
+    pub fn rain(capacity: usize) -> Self {
+        Self {
+            fall: Vector3::new(0.0, -14.0, 0.0),
+            streak: 0.03,
+            sway: 0.0,
+            size: 0.0,
+            color: Color::new(170, 190, 220, 140),
+            ..Self::new(capacity, Vector3::new(24.0, 14.0, 24.0))
+        }
+    }
+
+    pub fn snow(capacity: usize) -> Self {
+        Self {
+            fall: Vector3::new(0.0, -1.2, 0.0),
+            streak: 0.0,
+            sway: 0.4,
+            size: 0.08,
+            color: Color::new(245, 245, 255, 220),
+            ..Self::new(capacity, Vector3::new(20.0, 12.0, 20.0))
+        }
+    }
+
+    fn new(capacity: usize, area: Vector3) -> Self {
+        // Spread over the box with the R3 low discrepancy sequence, random
+        // positions would leave clumps. Any prefix of it is evenly spread too,
+        // so light weather simply draws fewer of them.
+        let positions = (1..=capacity)
+            .map(|i| {
+                let i = i as f32;
+                Vector3::new(
+                    (i * 0.819_172_5).fract() * area.x,
+                    (i * 0.671_043_5).fract() * area.y,
+                    (i * 0.549_700_5).fract() * area.z,
+                )
+            })
+            .collect();
+
+        Self {
+            area,
+            fall: Vector3::zero(),
+            streak: 0.0,
+            sway: 0.0,
+            size: 0.0,
+            color: Color::WHITE,
+            positions,
+            amount: 0.0,
+            wind: Vector3::zero(),
+            time: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32, amount: f32, wind: (f32, f32)) {
+        self.amount = amount.clamp(0.0, 1.0);
+        self.wind = Vector3::new(wind.0, 0.0, wind.1);
+        self.time += delta_time;
+        if self.amount <= 0.0 {
+            return;
+        }
+
+        // Stored positions stay inside the box, so they keep their precision.
+        // Drawing wraps them again into the box around the camera.
+        let step = self.velocity() * delta_time;
+        for position in &mut self.positions {
+            *position = wrap_position(*position + step, Vector3::zero(), self.area);
+        }
+    }
+
+    fn velocity(&self) -> Vector3 {
+        self.fall + self.wind
+    }
+
+    // Draw after the opaque scene, like the 3D particles
+    pub fn draw(
+        &self,
+        d: &mut (impl RaylibDraw + RaylibDraw3D),
+        camera: &Camera3D,
+        texture: &Texture2D,
+        unlit_shader: &Shader,
+        fog: Option<(Color, f32)>,
+    ) {
+        let count = (self.positions.len() as f32 * self.amount) as usize;
+        if count == 0 {
+            return;
+        }
+
+        let mut d = d.begin_shader_mode(unlit_shader);
+        let mut d = d.begin_blend_mode(BlendMode::BLEND_ALPHA);
+        unsafe {
+            ffi::rlDrawRenderBatchActive();
+            ffi::rlDisableDepthMask();
+        }
+
+        let origin = camera.position - self.area * 0.5;
+        let streak = self.velocity() * self.streak;
+
+        for (i, position) in self.positions[..count].iter().enumerate() {
+            let mut position = origin + wrap_position(*position, origin, self.area);
+            if self.sway > 0.0 {
+                let phase = self.time * 1.5 + i as f32;
+                position += Vector3::new(phase.sin(), 0.0, (phase * 0.7).cos()) * self.sway;
+            }
+
+            let mut color = self.color;
+            if let Some((fog_color, fog_density)) = fog {
+                let distance = position.distance_to(camera.position);
+                let fog_factor = fog_factor(distance, fog_density);
+                color = apply_fog(color, fog_color, fog_factor);
+            }
+
+            if self.streak > 0.0 {
+                d.draw_line_3D(position, position - streak, color);
+            } else {
+                d.draw_billboard(*camera, texture, position, self.size, color);
+            }
+        }
+
+        unsafe {
+            ffi::rlDrawRenderBatchActive();
+            ffi::rlEnableDepthMask();
+        }
+    }
+}
+
+// Position relative to the origin, wrapped into the box of the given size
+pub fn wrap_position(position: Vector3, origin: Vector3, size: Vector3) -> Vector3 {
+    let wrap = |p: f32, origin: f32, size: f32| (p - origin).rem_euclid(size);
+
+    Vector3::new(
+        wrap(position.x, origin.x, size.x),
+        wrap(position.y, origin.y, size.y),
+        wrap(position.z, origin.z, size.z),
+    )
+}